@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use rayonetta::bvh::{BvhTree, BVH};
+use rayonetta::color::Color;
+use rayonetta::hittable::{HitRecord, Hittable};
+use rayonetta::interval::Interval;
+use rayonetta::material::Lambertian;
+use rayonetta::planar::create_box;
+use rayonetta::ray::Ray;
+use rayonetta::sphere::Sphere;
+use rayonetta::utils::{random_interval, random_uniform, Rng, INFINITY};
+use rayonetta::vec3::{unit_vector, Point3, Vec3};
+
+// Compares the flattened BVH with the pointer based tree it is built from, on the
+// geometry of the thousandballs example and of the final scene. Run with
+//     cargo bench --bench bvh
+
+// The small spheres of the thousandballs example and its three large ones
+fn thousandballs() -> Vec<Arc<dyn Hittable>> {
+    let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let mut rng = Rng::new(0);
+    let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
+    for a in -11..11 {
+        for b in -11..11 {
+            let center = Point3::new(a as f64 + 0.9 * random_uniform(&mut rng), 0.2, b as f64 + 0.9 * random_uniform(&mut rng));
+            objects.push(Arc::new(Sphere::new(center, 0.2, material.clone())));
+        }
+    }
+    for x in [-4.0, 0.0, 4.0] {
+        objects.push(Arc::new(Sphere::new(Point3::new(x, 1.0, 0.0), 1.0, material.clone())));
+    }
+    objects
+}
+
+// The ground boxes and the bubbles of the final scene, in one hierarchy
+fn final_scene() -> Vec<Arc<dyn Hittable>> {
+    let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let mut rng = Rng::new(0);
+    let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
+    for i in 0..20 {
+        for j in 0..20 {
+            let a = Point3::new(-1000.0 + i as f64 * 100.0, 0.0, -1000.0 + j as f64 * 100.0);
+            let b = a + Vec3::new(100.0, random_interval(1.0, 101.0, &mut rng), 100.0);
+            objects.push(create_box(a, b, material.clone()));
+        }
+    }
+    for _ in 0..1000 {
+        let center = Point3::random_range(0.0, 165.0, &mut rng) + Vec3::new(-100.0, 270.0, 395.0);
+        objects.push(Arc::new(Sphere::new(center, 10.0, material.clone())));
+    }
+    objects
+}
+
+// Rays from the camera of each scene towards random points around what it looks at
+fn camera_rays(lookfrom: Point3, lookat: Point3, spread: f64, count: usize) -> Vec<Ray> {
+    let mut rng = Rng::new(1);
+    (0..count)
+        .map(|_| {
+            let target = lookat + Vec3::random_range(-spread, spread, &mut rng);
+            Ray::new(lookfrom, unit_vector(target - lookfrom))
+        })
+        .collect()
+}
+
+fn trace(world: &dyn Hittable, rays: &[Ray]) -> usize {
+    let mut rec = HitRecord::new();
+    rays.iter().filter(|r| world.hit(r, &mut Interval::new(0.001, INFINITY), &mut rec)).count()
+}
+
+fn bench_scene(c: &mut Criterion, name: &str, objects: Vec<Arc<dyn Hittable>>, rays: Vec<Ray>) {
+    let mut group = c.benchmark_group(name);
+
+    group.bench_function(BenchmarkId::new("build", "tree"), |b| b.iter(|| BvhTree::new(&mut objects.clone())));
+    group.bench_function(BenchmarkId::new("build", "flat"), |b| b.iter(|| BVH::new(&mut objects.clone())));
+
+    let tree = BvhTree::new(&mut objects.clone());
+    let flat = BVH::new(&mut objects.clone());
+    assert_eq!(trace(&tree, &rays), trace(&flat, &rays));
+    group.bench_function(BenchmarkId::new("trace", "tree"), |b| b.iter(|| trace(&tree, &rays)));
+    group.bench_function(BenchmarkId::new("trace", "flat"), |b| b.iter(|| trace(&flat, &rays)));
+
+    group.finish();
+}
+
+fn bvh_benchmarks(c: &mut Criterion) {
+    let rays = camera_rays(Point3::new(13.0, 2.0, 3.0), Point3::new(0.0, 0.0, 0.0), 6.0, 10_000);
+    bench_scene(c, "thousandballs", thousandballs(), rays);
+
+    let rays = camera_rays(Point3::new(478.0, 278.0, -600.0), Point3::new(278.0, 278.0, 0.0), 400.0, 10_000);
+    bench_scene(c, "final_scene", final_scene(), rays);
+}
+
+criterion_group!(benches, bvh_benchmarks);
+criterion_main!(benches);
@@ -7,14 +7,23 @@ use rayonetta::bvh::BVH;
 use rayonetta::camera::Camera;
 use rayonetta::color::Color;
 use rayonetta::constant_medium::ConstantMedium;
+use rayonetta::environment::EnvironmentLight;
 use rayonetta::hittable_list::HittableList;
+use rayonetta::integrator::{
+    AlbedoIntegrator, AmbientOcclusionIntegrator, DirectLightingIntegrator, NormalIntegrator, PathIntegrator,
+    UvIntegrator,
+};
 use rayonetta::material::{Dielectric, DiffuseLight, Lambertian, Metal};
+use rayonetta::mesh::{MeshData, TriangleMesh};
 use rayonetta::planar::{create_box, Quadrilateral};
 use rayonetta::plane::Plane;
+use rayonetta::ply::load_ply;
+use rayonetta::scene::load_scene;
 use rayonetta::sphere::Sphere;
+use rayonetta::stl::load_stl;
 use rayonetta::texture::{CheckerTexture, ImageTexture, NoiseTexture};
 use rayonetta::transformations::{RotateY, Translate};
-use rayonetta::utils::{random_interval, random_uniform};
+use rayonetta::utils::{random_interval, random_uniform, Rng};
 use rayonetta::vec3::{Point3, Vec3};
 
 /// This program selects the raytracing demo
@@ -24,28 +33,89 @@ struct Args {
     /// Demo number to show
     #[arg(short, long, default_value_t = 0)]
     demo_number: usize,
+
+    /// Light transport algorithm, the others are debug views
+    #[arg(short, long, value_enum, default_value_t = IntegratorChoice::Path)]
+    integrator: IntegratorChoice,
+
+    /// Occlusion distance for the ambient occlusion view
+    #[arg(long, default_value_t = 1.0)]
+    ao_distance: f64,
+
+    /// Equirectangular .hdr or .exr map lighting the scene instead of the background
+    #[arg(short, long)]
+    environment: Option<String>,
+
+    /// Radiance scale of the environment map
+    #[arg(long, default_value_t = 1.0)]
+    environment_intensity: f64,
+
+    /// Rotation of the environment map around the vertical axis, in degrees
+    #[arg(long, default_value_t = 0.0)]
+    environment_rotation: f64,
+
+    /// .ply or .stl mesh shown by the mesh demo
+    #[arg(short, long, default_value = "assets/icosahedron.ply")]
+    mesh: String,
+
+    /// TOML scene file to render instead of a demo, see scenes/
+    #[arg(short, long)]
+    scene: Option<String>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum IntegratorChoice {
+    Path,
+    Direct,
+    Ao,
+    Normal,
+    Uv,
+    Albedo,
+}
+
+// Applies the command line render options to a demo camera
+fn configure(cam: &mut Camera, args: &Args) {
+    cam.integrator = match args.integrator {
+        IntegratorChoice::Path => Arc::new(PathIntegrator),
+        IntegratorChoice::Direct => Arc::new(DirectLightingIntegrator),
+        IntegratorChoice::Ao => Arc::new(AmbientOcclusionIntegrator::new(args.ao_distance)),
+        IntegratorChoice::Normal => Arc::new(NormalIntegrator),
+        IntegratorChoice::Uv => Arc::new(UvIntegrator),
+        IntegratorChoice::Albedo => Arc::new(AlbedoIntegrator),
+    };
+
+    if let Some(path) = &args.environment {
+        let mut environment = match EnvironmentLight::from_file(path) {
+            Ok(environment) => environment,
+            Err(e) => panic!("Error opening environment map {path}: {e}"),
+        };
+        environment.intensity = args.environment_intensity;
+        environment.rotation = args.environment_rotation;
+        cam.environment = Some(Arc::new(environment));
+    }
 }
 
-fn bouncing_spheres() {
+fn bouncing_spheres(args: &Args) {
     // World
     let mut world = HittableList::new();
+    let mut rng = Rng::new(0);
 
     // Smaller balls
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = random_uniform();
+            let choose_mat = random_uniform(&mut rng);
             let center = Point3::new(
-                a as f64 + 0.9 * random_uniform(),
+                a as f64 + 0.9 * random_uniform(&mut rng),
                 0.2,
-                b as f64 + 0.9 * random_uniform(),
+                b as f64 + 0.9 * random_uniform(&mut rng),
             );
 
             if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 if choose_mat < 0.8 {
                     // Diffuse
-                    let albedo = Vec3::random() * Vec3::random();
+                    let albedo = Vec3::random(&mut rng) * Vec3::random(&mut rng);
                     let sphere_material = Arc::new(Lambertian::new(albedo));
-                    let center2 = center + Vec3::new(0.0, random_interval(0.0, 0.5), 0.0);
+                    let center2 = center + Vec3::new(0.0, random_interval(0.0, 0.5, &mut rng), 0.0);
                     world.add(Arc::new(Sphere::new_dynamic(
                         center,
                         center2,
@@ -54,8 +124,8 @@ fn bouncing_spheres() {
                     )));
                 } else if choose_mat < 0.95 {
                     // Metal
-                    let albedo = Color::random_range(0.5, 1.0);
-                    let fuzz = random_interval(0.0, 0.5);
+                    let albedo = Color::random_range(0.5, 1.0, &mut rng);
+                    let fuzz = random_interval(0.0, 0.5, &mut rng);
                     let sphere_material = Arc::new(Metal::new(albedo, fuzz));
                     world.add(Arc::new(Sphere::new(center, 0.2, sphere_material.clone())));
                 } else {
@@ -88,9 +158,6 @@ fn bouncing_spheres() {
         material3.clone(),
     )));
 
-    // We now change the world to a BVH
-    world = HittableList::from_object(Arc::new(BVH::from_hittable(world)));
-
     // Ground Plane
     let checker_texture = Arc::new(CheckerTexture::from_color(
         0.32,
@@ -104,11 +171,15 @@ fn bouncing_spheres() {
         material_ground,
     )));
 
+    // We now change the world to a BVH
+    world = HittableList::from_object(Arc::new(BVH::from_hittable(world)));
+
     // Camera settings
     let aspect_ratio = 16.0 / 9.0;
     let image_width = 400;
     let samples_per_pixel = 100;
-    let max_depth = 50;
+    // Russian roulette ends most paths early, so deep glass paths are cheap
+    let max_depth = 200;
 
     let vfov = 20.0;
     let lookfrom = Point3::new(13.0, 2.0, 3.0);
@@ -137,10 +208,11 @@ fn bouncing_spheres() {
     cam.background = background;
 
     // Render
+    configure(&mut cam, args);
     cam.render(&world);
 }
 
-fn checkered_spheres() {
+fn checkered_spheres(args: &Args) {
     // World
     let mut world = HittableList::new();
 
@@ -195,10 +267,11 @@ fn checkered_spheres() {
     cam.background = background;
 
     // Render
+    configure(&mut cam, args);
     cam.render(&world);
 }
 
-fn earth() {
+fn earth(args: &Args) {
     let earth_texture = Arc::new(ImageTexture::from_image("assets/mars.webp"));
     let earth_surface = Arc::new(Lambertian::from_texture(earth_texture));
     let globe = Arc::new(Sphere::new(Point3::empty(), 2.0, earth_surface.clone()));
@@ -236,14 +309,16 @@ fn earth() {
     // Render
     let mut world = HittableList::new();
     world.add(globe);
+    configure(&mut cam, args);
     cam.render(&world);
 }
 
-fn perlin_spheres() {
+fn perlin_spheres(args: &Args) {
     // Creating the precious world
     let mut world = HittableList::new();
+    let mut rng = Rng::new(0);
 
-    let perlin_texture = Arc::new(NoiseTexture::new(4.0));
+    let perlin_texture = Arc::new(NoiseTexture::new(4.0, &mut rng));
     let perlin_material = Arc::new(Lambertian::from_texture(perlin_texture));
     let sphere = Arc::new(Sphere::new(
         Point3::new(0.0, 2.0, 0.0),
@@ -290,10 +365,11 @@ fn perlin_spheres() {
     cam.background = background;
 
     // Render
+    configure(&mut cam, args);
     cam.render(&world);
 }
 
-fn quadrilaterals() {
+fn quadrilaterals(args: &Args) {
     let mut world = HittableList::new();
 
     let red = Arc::new(Lambertian::new(Color::new(0.8, 0.2, 0.1)));
@@ -368,13 +444,15 @@ fn quadrilaterals() {
     cam.background = background;
 
     // Render
+    configure(&mut cam, args);
     cam.render(&world);
 }
 
-fn simple_light() {
+fn simple_light(args: &Args) {
     let mut world = HittableList::new();
+    let mut rng = Rng::new(0);
 
-    let perlin_texture = Arc::new(NoiseTexture::new(4.0));
+    let perlin_texture = Arc::new(NoiseTexture::new(4.0, &mut rng));
     let perlin_material = Arc::new(Lambertian::from_texture(perlin_texture));
     let sphere = Arc::new(Sphere::new(
         Point3::new(0.0, 2.0, 0.0),
@@ -397,13 +475,14 @@ fn simple_light() {
         Vec3::new(0.0, 2.0, 0.0),
         difflight.clone(),
     ));
-
-    world.add(quadlight);
-    world.add(Arc::new(Sphere::new(
+    let spherelight = Arc::new(Sphere::new(
         Point3::new(0.0, 7.0, 0.0),
         2.0,
         difflight.clone(),
-    )));
+    ));
+
+    world.add(quadlight.clone());
+    world.add(spherelight.clone());
 
     // Camera settings
     let aspect_ratio = 16.0 / 9.0;
@@ -433,11 +512,16 @@ fn simple_light() {
     // Focus blur
     cam.defocus_angle = defocus_angle;
 
+    // Sample the lights directly
+    cam.lights.add(quadlight);
+    cam.lights.add(spherelight);
+
     // Render
+    configure(&mut cam, args);
     cam.render(&world);
 }
 
-fn cornell_box() {
+fn cornell_box(args: &Args) {
     let mut world = HittableList::new();
 
     let red = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
@@ -445,12 +529,13 @@ fn cornell_box() {
     let green = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
     let light = Arc::new(DiffuseLight::from_color(Color::new(15.0, 15.0, 15.0)));
 
-    world.add(Arc::new(Quadrilateral::new(
+    let ceiling_light = Arc::new(Quadrilateral::new(
         Point3::new(343.0, 554.0, 332.0),
         Vec3::new(-130.0, 0.0, 0.0),
         Vec3::new(0.0, 0.0, -105.0),
         light.clone(),
-    )));
+    ));
+    world.add(ceiling_light.clone());
 
     world.add(Arc::new(Quadrilateral::new(
         Point3::new(555.0, 0.0, 0.0),
@@ -521,10 +606,13 @@ fn cornell_box() {
 
     cam.defocus_angle = 0.0;
 
+    cam.lights.add(ceiling_light);
+
+    configure(&mut cam, args);
     cam.render(&world);
 }
 
-fn cornell_smoke() {
+fn cornell_smoke(args: &Args) {
     let mut world = HittableList::new();
 
     let red = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
@@ -532,12 +620,13 @@ fn cornell_smoke() {
     let green = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
     let light = Arc::new(DiffuseLight::from_color(Color::new(7.0, 7.0, 7.0)));
 
-    world.add(Arc::new(Quadrilateral::new(
+    let ceiling_light = Arc::new(Quadrilateral::new(
         Point3::new(113.0, 554.0, 127.0),
         Vec3::new(330.0, 0.0, 0.0),
         Vec3::new(0.0, 0.0, 305.0),
         light.clone(),
-    )));
+    ));
+    world.add(ceiling_light.clone());
 
     world.add(Arc::new(Quadrilateral::new(
         Point3::new(555.0, 0.0, 0.0),
@@ -619,12 +708,102 @@ fn cornell_smoke() {
 
     cam.defocus_angle = 0.0;
 
+    cam.lights.add(ceiling_light);
+
+    configure(&mut cam, args);
+    cam.render(&world);
+}
+
+fn mesh_spheres(args: &Args) {
+    let path = &args.mesh;
+    let loaded = if path.to_lowercase().ends_with(".stl") {
+        load_stl(path).map_err(|e| e.to_string())
+    } else {
+        load_ply(path).map_err(|e| e.to_string())
+    };
+    let mut data: MeshData = match loaded {
+        Ok(data) => data,
+        Err(e) => panic!("Error opening mesh {e}"),
+    };
+
+    // Scanned and CAD models come in any unit, fit the mesh in a 2 unit box standing on the ground
+    let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+    let mut max = -min;
+    for p in &data.positions {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+    let extent = (max - min).max_component();
+    let base = Point3::new(0.5 * (min.x() + max.x()), min.y(), 0.5 * (min.z() + max.z()));
+    for p in data.positions.iter_mut() {
+        *p = (*p - base) * (2.0 / extent);
+    }
+
+    let mesh = if data.colors.is_empty() {
+        TriangleMesh::new(data, Arc::new(Lambertian::new(Color::new(0.8, 0.6, 0.4))))
+    } else {
+        TriangleMesh::with_vertex_colors(data)
+    };
+    let mesh = match mesh {
+        Ok(mesh) => mesh,
+        Err(e) => panic!("Error building mesh {path}: {e}"),
+    };
+
+    // World
+    let mut world = HittableList::new();
+    world.add(Arc::new(mesh));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, -2.5),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 2.5),
+        1.0,
+        Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0)),
+    )));
+
+    // Ground Plane
+    let checker_texture = Arc::new(CheckerTexture::from_color(
+        0.32,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ));
+    world.add(Arc::new(Plane::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        Point3::empty(),
+        Arc::new(Lambertian::from_texture(checker_texture)),
+    )));
+
+    world = HittableList::from_object(Arc::new(BVH::from_hittable(world)));
+
+    // Camera settings
+    let mut cam = Camera::new();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 50;
+
+    cam.vfov = 30.0;
+    cam.lookfrom = Point3::new(13.0, 3.0, 0.0);
+    cam.lookat = Point3::new(0.0, 1.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+    cam.background = Color::new(0.70, 0.80, 1.0);
+
+    // Render
+    configure(&mut cam, args);
     cam.render(&world);
 }
 
-fn final_scene(image_width: i32, samples_per_pixel: i32, max_depth: i32) {
+fn final_scene(image_width: i32, samples_per_pixel: i32, max_depth: i32, args: &Args) {
     // Our precious world
     let mut world = HittableList::new();
+    let mut rng = Rng::new(0);
 
     // Groundwork
     let mut ground_boxes = HittableList::new();
@@ -638,7 +817,7 @@ fn final_scene(image_width: i32, samples_per_pixel: i32, max_depth: i32) {
             let z0 = -1000.0 + (j as f64) * w;
             let y0 = 0.0;
             let x1 = x0 + w;
-            let y1 = random_interval(1.0, 101.0);
+            let y1 = random_interval(1.0, 101.0, &mut rng);
             let z1 = z0 + w;
 
             ground_boxes.add(create_box(
@@ -652,12 +831,13 @@ fn final_scene(image_width: i32, samples_per_pixel: i32, max_depth: i32) {
     world.add(Arc::new(BVH::from_hittable(ground_boxes)));
 
     let light = Arc::new(DiffuseLight::from_color(Color::new(7.0, 7.0, 7.0)));
-    world.add(Arc::new(Quadrilateral::new(
+    let ceiling_light = Arc::new(Quadrilateral::new(
         Point3::new(123.0, 554.0, 147.0),
         Vec3::new(300.0, 0.0, 0.0),
         Vec3::new(0.0, 0.0, 265.0),
         light.clone(),
-    )));
+    ));
+    world.add(ceiling_light.clone());
 
     // Moving Sphere
     let center1 = Point3::new(400.0, 400.0, 200.0);
@@ -717,7 +897,7 @@ fn final_scene(image_width: i32, samples_per_pixel: i32, max_depth: i32) {
     )));
 
     // Perlin Texture
-    let perlin_texture = Arc::new(NoiseTexture::new(0.2));
+    let perlin_texture = Arc::new(NoiseTexture::new(0.2, &mut rng));
     world.add(Arc::new(Sphere::new(
         Point3::new(220.0, 280.0, 300.0),
         80.0,
@@ -730,7 +910,7 @@ fn final_scene(image_width: i32, samples_per_pixel: i32, max_depth: i32) {
     let ns = 1000;
     for _ in 0..ns {
         bubble_box.add(Arc::new(Sphere::new(
-            Point3::random_range(0.0, 165.0),
+            Point3::random_range(0.0, 165.0, &mut rng),
             10.0,
             white.clone(),
         )));
@@ -758,6 +938,9 @@ fn final_scene(image_width: i32, samples_per_pixel: i32, max_depth: i32) {
 
     cam.defocus_angle = 0.0;
 
+    cam.lights.add(ceiling_light);
+
+    configure(&mut cam, args);
     cam.render(&world);
 }
 
@@ -770,16 +953,27 @@ fn main() {
     env_logger::init_from_env(env);
 
     let args = Args::parse();
+    if let Some(path) = &args.scene {
+        // Scene files carry their own camera and render settings
+        let scene = match load_scene(path) {
+            Ok(scene) => scene,
+            Err(e) => panic!("Error loading scene {e}"),
+        };
+        scene.camera.render(&scene.world);
+        return;
+    }
+
     match args.demo_number {
-        1 => bouncing_spheres(),
-        2 => checkered_spheres(),
-        3 => earth(),
-        4 => perlin_spheres(),
-        5 => quadrilaterals(),
-        6 => simple_light(),
-        7 => cornell_box(),
-        8 => cornell_smoke(),
-        9 => final_scene(800, 10000, 40),
-        _ => final_scene(400, 250, 4),
+        1 => bouncing_spheres(&args),
+        2 => checkered_spheres(&args),
+        3 => earth(&args),
+        4 => perlin_spheres(&args),
+        5 => quadrilaterals(&args),
+        6 => simple_light(&args),
+        7 => cornell_box(&args),
+        8 => cornell_smoke(&args),
+        9 => final_scene(800, 10000, 40, &args),
+        10 => mesh_spheres(&args),
+        _ => final_scene(400, 250, 4, &args),
     }
 }
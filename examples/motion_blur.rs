@@ -9,7 +9,7 @@ use rayonetta::hittable_list::HittableList;
 use rayonetta::material::{Dielectric, Lambertian, Metal};
 use rayonetta::plane::Plane;
 use rayonetta::sphere::Sphere;
-use rayonetta::utils::{random_interval, random_uniform};
+use rayonetta::utils::{random_interval, random_uniform, Rng};
 use rayonetta::vec3::{Point3, Vec3};
 
 fn main() {
@@ -22,24 +22,25 @@ fn main() {
 
     // World
     let mut world = HittableList::new();
+    let mut rng = Rng::new(0);
 
     // Smaller balls
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = random_uniform();
-            let center = Point3::new(a as f64 + 0.9*random_uniform(), 0.2, b as f64 + 0.9*random_uniform());
+            let choose_mat = random_uniform(&mut rng);
+            let center = Point3::new(a as f64 + 0.9*random_uniform(&mut rng), 0.2, b as f64 + 0.9*random_uniform(&mut rng));
 
             if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 if choose_mat < 0.8 {
                     // Diffuse
-                    let albedo = Vec3::random() * Vec3::random();
+                    let albedo = Vec3::random(&mut rng) * Vec3::random(&mut rng);
                     let sphere_material = Arc::new(Lambertian::new(albedo));
-                    let center2 = center + Vec3::new(0.0, random_interval(0.0, 0.5), 0.0);
+                    let center2 = center + Vec3::new(0.0, random_interval(0.0, 0.5, &mut rng), 0.0);
                     world.add(Arc::new(Sphere::new_dynamic(center, center2, 0.2, sphere_material.clone())));
                 } else if choose_mat < 0.95 {
                     // Metal
-                    let albedo = Color::random_range(0.5, 1.0);
-                    let fuzz = random_interval(0.0, 0.5);
+                    let albedo = Color::random_range(0.5, 1.0, &mut rng);
+                    let fuzz = random_interval(0.0, 0.5, &mut rng);
                     let sphere_material = Arc::new(Metal::new(albedo, fuzz));
                     world.add(Arc::new(Sphere::new(center, 0.2, sphere_material.clone())));
                 } else {
@@ -60,9 +61,6 @@ fn main() {
     world.add(Arc::new(Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, material2.clone())));
     world.add(Arc::new(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, material3.clone())));
 
-    // We now change the world to a BVH
-    world = HittableList::from_object(Arc::new(BVH::from_hittable(world)));
-
     // Ground Plane
     let material_ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
     world.add(Arc::new(Plane::new(
@@ -71,6 +69,9 @@ fn main() {
         material_ground
     )));
 
+    // We now change the world to a BVH
+    world = HittableList::from_object(Arc::new(BVH::from_hittable(world)));
+
     // Camera settings
     let aspect_ratio = 16.0 / 9.0;
     let image_width = 400;
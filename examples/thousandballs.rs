@@ -8,7 +8,7 @@ use rayonetta::hittable_list::HittableList;
 use rayonetta::material::{Dielectric, Lambertian, Metal};
 use rayonetta::plane::Plane;
 use rayonetta::sphere::Sphere;
-use rayonetta::utils::{random_interval, random_uniform};
+use rayonetta::utils::{random_interval, random_uniform, Rng};
 use rayonetta::vec3::{Point3, Vec3};
 
 fn main() {
@@ -21,6 +21,7 @@ fn main() {
 
     // World
     let mut world = HittableList::new();
+    let mut rng = Rng::new(0);
     
     // Ground Plane
     let material_ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
@@ -33,19 +34,19 @@ fn main() {
     // Smaller balls
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = random_uniform();
-            let center = Point3::new(a as f64 + 0.9*random_uniform(), 0.2, b as f64 + 0.9*random_uniform());
+            let choose_mat = random_uniform(&mut rng);
+            let center = Point3::new(a as f64 + 0.9*random_uniform(&mut rng), 0.2, b as f64 + 0.9*random_uniform(&mut rng));
 
             if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 if choose_mat < 0.8 {
                     // Diffuse
-                    let albedo = Vec3::random() * Vec3::random();
+                    let albedo = Vec3::random(&mut rng) * Vec3::random(&mut rng);
                     let sphere_material = Arc::new(Lambertian::new(albedo));
                     world.add(Arc::new(Sphere::new(center, 0.2, sphere_material.clone())));
                 } else if choose_mat < 0.95 {
                     // Metal
-                    let albedo = Color::random_range(0.5, 1.0);
-                    let fuzz = random_interval(0.0, 0.5);
+                    let albedo = Color::random_range(0.5, 1.0, &mut rng);
+                    let fuzz = random_interval(0.0, 0.5, &mut rng);
                     let sphere_material = Arc::new(Metal::new(albedo, fuzz));
                     world.add(Arc::new(Sphere::new(center, 0.2, sphere_material.clone())));
                 } else {
@@ -44,13 +44,18 @@ impl AABB {
     }
 
     pub fn hit(&self, r: &Ray, ray: Interval) -> bool {
-        let mut ray_t = ray;
-        let ray_orig = r.origin();
         let ray_dir = r.direction();
+        let inv_dir = Vec3::new(1.0 / ray_dir[0], 1.0 / ray_dir[1], 1.0 / ray_dir[2]);
+        self.hit_inverse(r.origin(), inv_dir, ray)
+    }
+
+    // Slab test with the reciprocal of the ray direction, for callers testing many boxes against one ray
+    pub fn hit_inverse(&self, ray_orig: Point3, inv_dir: Vec3, ray: Interval) -> bool {
+        let mut ray_t = ray;
 
         for axis in 0..3 {
             let ax = self.axis_interval(axis);
-            let adinv = 1.0 / ray_dir[axis];
+            let adinv = inv_dir[axis];
 
             let t0 = (ax.min - ray_orig[axis]) * adinv;
             let t1 = (ax.max - ray_orig[axis]) * adinv;
@@ -87,6 +92,28 @@ impl AABB {
         }
     }
 
+    // Empty boxes have no area
+    pub fn surface_area(&self) -> f64 {
+        let (dx, dy, dz) = (self.x.size(), self.y.size(), self.z.size());
+        if dx < 0.0 || dy < 0.0 || dz < 0.0 {
+            return 0.0;
+        }
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    // False for infinite boxes, for the empty box and for bounds that went NaN
+    pub fn is_bounded(&self) -> bool {
+        [self.x, self.y, self.z].iter().all(|i| i.min.is_finite() && i.max.is_finite() && i.min <= i.max)
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            0.5 * (self.x.min + self.x.max),
+            0.5 * (self.y.min + self.y.max),
+            0.5 * (self.z.min + self.z.max),
+        )
+    }
+
     fn pad_to_minimum(&mut self) {
 
         let delta = 0.0001;
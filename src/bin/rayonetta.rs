@@ -0,0 +1,226 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+use clap::{Args, Parser, Subcommand};
+use env_logger::Env;
+use log::info;
+
+use rayonetta::camera::{FisheyeMapping, Projection};
+use rayonetta::hittable::Hittable;
+use rayonetta::output::save_image;
+use rayonetta::scene::{load_scene, parse_scene, Scene};
+use rayonetta::shutter::ShutterCurve;
+
+// Scene files shipped with the renderer, usable by name. Their textures are looked up
+// relative to scenes/, so they render from the repository root
+const PRESETS: [(&str, &str); 10] = [
+    ("bouncing_spheres", include_str!("../../scenes/bouncing_spheres.toml")),
+    ("checkered_spheres", include_str!("../../scenes/checkered_spheres.toml")),
+    ("earth", include_str!("../../scenes/earth.toml")),
+    ("perlin_spheres", include_str!("../../scenes/perlin_spheres.toml")),
+    ("quadrilaterals", include_str!("../../scenes/quadrilaterals.toml")),
+    ("simple_light", include_str!("../../scenes/simple_light.toml")),
+    ("cornell_box", include_str!("../../scenes/cornell_box.toml")),
+    ("cornell_smoke", include_str!("../../scenes/cornell_smoke.toml")),
+    ("final_scene", include_str!("../../scenes/final_scene.toml")),
+    ("tumbling_boxes", include_str!("../../scenes/tumbling_boxes.toml")),
+];
+
+/// Renders TOML scene files
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Renders a scene file or preset to an image
+    Render(RenderArgs),
+
+    /// Prints the camera settings, object counts and bounds of a scene
+    Info {
+        /// Scene file, or the name of a preset
+        scene: String,
+    },
+
+    /// Lists the built in scenes
+    Presets,
+}
+
+#[derive(Args, Debug)]
+struct RenderArgs {
+    /// Scene file, or the name of a preset
+    scene: String,
+
+    /// Output image, the format follows the extension (png, jpg, ppm, hdr, exr)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Samples per pixel, overriding the scene
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1..))]
+    spp: Option<i32>,
+
+    /// Image width in pixels, overriding the scene. The aspect ratio is kept
+    #[arg(short, long, value_parser = clap::value_parser!(i32).range(1..))]
+    width: Option<i32>,
+
+    /// Maximum path depth, overriding the scene
+    #[arg(short, long, value_parser = clap::value_parser!(i32).range(1..))]
+    depth: Option<i32>,
+
+    /// Worker threads, all cores by default
+    #[arg(short, long)]
+    threads: Option<usize>,
+
+    /// Random seed, overriding the scene
+    #[arg(short, long)]
+    seed: Option<u64>,
+
+    /// Also saves the sample counts of adaptive sampling as a false color image, blue for the
+    /// fewest and red for the most
+    #[arg(long)]
+    heatmap: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let env = Env::default()
+        .filter_or("MY_LOG_LEVEL", "info")
+        .write_style_or("MY_LOG_STYLE", "always");
+    env_logger::init_from_env(env);
+
+    let result = match Cli::parse().command {
+        Command::Render(args) => render(args),
+        Command::Info { scene } => open(&scene).map(|scene| print_info(&scene)),
+        Command::Presets => {
+            for (name, source) in PRESETS {
+                println!("{name:<20}{}", description(source));
+            }
+            Ok(())
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Loads a scene file, falling back to the presets when no such file exists
+fn open(scene: &str) -> Result<Scene, String> {
+    if !Path::new(scene).exists() {
+        if let Some((name, source)) = PRESETS.iter().find(|(name, _)| *name == scene) {
+            let path = Path::new("scenes").join(format!("{name}.toml"));
+            return parse_scene(source, &path).map_err(|e| e.to_string());
+        }
+        return Err(format!("No scene file or preset named '{scene}', see the presets command"));
+    }
+    load_scene(scene).map_err(|e| e.to_string())
+}
+
+// The first comment line of a scene file
+fn description(source: &str) -> &str {
+    source.lines().find_map(|line| line.strip_prefix('#')).unwrap_or("").trim()
+}
+
+fn render(args: RenderArgs) -> Result<(), String> {
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new().num_threads(threads).build_global().map_err(|e| e.to_string())?;
+    }
+
+    let mut scene = open(&args.scene)?;
+    let cam = &mut scene.camera;
+    if let Some(spp) = args.spp {
+        cam.samples_per_pixel = spp;
+    }
+    if let Some(width) = args.width {
+        cam.image_width = width;
+    }
+    if let Some(depth) = args.depth {
+        cam.max_depth = depth;
+    }
+    if let Some(seed) = args.seed {
+        cam.seed = seed;
+    }
+    // Uniform sampling takes the same number of samples everywhere, so there is nothing to show
+    if args.heatmap.is_some() && !cam.adaptive_sampling {
+        return Err("--heatmap needs adaptive_sampling = true in the scene's [camera]".to_string());
+    }
+
+    // Next to the working directory, named after the scene
+    let output = args.output.unwrap_or_else(|| {
+        let stem = Path::new(&args.scene).file_stem().map_or("render".into(), |s| s.to_string_lossy());
+        PathBuf::from(format!("{stem}.png"))
+    });
+
+    let start = Instant::now();
+    let image = cam.render_to_buffer(&scene.world);
+    info!("Rendered {}x{} in {:.1?}", image.width(), image.height(), start.elapsed());
+
+    save_image(&image, &output)?;
+    info!("Saved {}", output.display());
+    if let Some(heatmap) = args.heatmap {
+        save_image(&image.sample_heatmap(), &heatmap)?;
+        info!("Saved the sample heatmap to {}", heatmap.display());
+    }
+    Ok(())
+}
+
+fn print_info(scene: &Scene) {
+    let cam = &scene.camera;
+    let height = ((cam.image_width as f64 / cam.aspect_ratio) as i32).max(1);
+    println!("image        {}x{}, {} spp, depth {}", cam.image_width, height, cam.samples_per_pixel, cam.max_depth);
+    let view = match cam.projection {
+        Projection::Perspective => format!("vfov {}", cam.vfov),
+        Projection::Orthographic => format!("orthographic, height {}", cam.ortho_height),
+        Projection::Equirectangular => "equirectangular".to_string(),
+        Projection::Fisheye(mapping) => {
+            let mapping = match mapping {
+                FisheyeMapping::Equidistant => "equidistant",
+                FisheyeMapping::Equisolid => "equisolid",
+            };
+            format!("{mapping} fisheye, fov {}", cam.fisheye_fov)
+        }
+    };
+    println!(
+        "camera       from ({}, {}, {}) to ({}, {}, {}), {view}",
+        cam.lookfrom.x(), cam.lookfrom.y(), cam.lookfrom.z(),
+        cam.lookat.x(), cam.lookat.y(), cam.lookat.z(),
+    );
+    let focus = match cam.focus_pixel {
+        Some((x, y)) => format!("auto at pixel ({x}, {y})"),
+        None => cam.focus_dist.to_string(),
+    };
+    println!("focus        {focus}, defocus angle {:.3}, exposure {}", cam.defocus_angle, cam.exposure);
+    let curve = match &cam.shutter_curve {
+        ShutterCurve::Box => "box",
+        ShutterCurve::Triangle => "triangle",
+        ShutterCurve::Custom(_) => "custom",
+    };
+    println!("shutter      {} to {}, {curve} curve", cam.shutter_open, cam.shutter_close);
+    println!("objects      {}", scene.world.list.len());
+    println!("primitives   {}", scene.primitive_count);
+    println!("lights       {}", cam.lights.list.len());
+
+    let mut materials: Vec<&String> = scene.materials.keys().collect();
+    materials.sort();
+    println!("materials    {} {materials:?}", materials.len());
+    let mut textures: Vec<&String> = scene.textures.keys().collect();
+    textures.sort();
+    println!("textures     {} {textures:?}", textures.len());
+
+    let bbox = scene.world.bounding_box();
+    if bbox.is_bounded() {
+        println!(
+            "bounds       ({}, {}, {}) to ({}, {}, {})",
+            bbox.x.min, bbox.y.min, bbox.z.min, bbox.x.max, bbox.y.max, bbox.z.max
+        );
+    } else if !scene.world.list.is_empty() {
+        println!("bounds       unbounded");
+    }
+}
@@ -1,100 +1,480 @@
 use std::sync::Arc;
 
-use crate::{aabb::AABB, hittable::Hittable, hittable_list::HittableList, interval::Interval};
+use log::debug;
 
-pub struct BVH {
-    pub left: Arc<dyn Hittable>,
-    pub right: Arc<dyn Hittable>,
-    pub bbox: AABB,
+use crate::aabb::AABB;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+// Settings of the surface area heuristic build
+#[derive(Clone, Copy, Debug)]
+pub struct BvhOptions {
+    // Leaves hold up to this many objects. Larger sets are always split
+    pub max_leaf_size: usize,
+    // Candidate split planes per axis
+    pub bins: usize,
+    // Cost of visiting a node, relative to intersecting one object
+    pub traversal_cost: f64,
+    pub intersection_cost: f64,
 }
 
-impl BVH {
-    pub fn from_hittable(world: HittableList) -> Self {
-        BVH::new(&mut world.list.clone())
+impl BvhOptions {
+    pub fn new() -> Self {
+        BvhOptions { max_leaf_size: 4, bins: 16, traversal_cost: 1.0, intersection_cost: 1.0 }
     }
+}
+
+impl Default for BvhOptions {
+    fn default() -> Self {
+        BvhOptions::new()
+    }
+}
+
+// Shape of a built tree. The SAH cost is the expected cost of tracing a ray that hits the root box
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BvhStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub object_count: usize,
+    // Objects without finite bounds, tested against every ray next to the tree
+    pub unbounded_count: usize,
+    pub depth: usize,
+    pub largest_leaf: usize,
+    pub sah_cost: f64,
+}
+
+enum BvhNode {
+    Leaf { bbox: AABB, objects: Vec<Arc<dyn Hittable>> },
+    // Children are ordered along the split axis, the first one holds the lower centroids
+    Interior { bbox: AABB, axis: usize, children: Box<[BvhNode; 2]> },
+}
 
+impl BvhNode {
+    fn bbox(&self) -> &AABB {
+        match self {
+            BvhNode::Leaf { bbox, .. } | BvhNode::Interior { bbox, .. } => bbox,
+        }
+    }
+
+    fn hit(&self, r: &Ray, ray_t: &mut Interval, rec: &mut HitRecord) -> bool {
+        // Exit early if bbox of this node is not hit
+        if !self.bbox().hit(r, *ray_t) {
+            return false;
+        }
+
+        match self {
+            BvhNode::Leaf { objects, .. } => {
+                let mut hit_anything = false;
+                let mut closest = ray_t.max;
+                for object in objects {
+                    if object.hit(r, &mut Interval::new(ray_t.min, closest), rec) {
+                        hit_anything = true;
+                        closest = rec.t;
+                    }
+                }
+                hit_anything
+            }
+            BvhNode::Interior { axis, children, .. } => {
+                // Near child first, so the far one is often culled by the shortened interval
+                let (first, second) =
+                    if r.direction()[*axis] < 0.0 { (&children[1], &children[0]) } else { (&children[0], &children[1]) };
+
+                let hit_first = first.hit(r, ray_t, rec);
+                let hit_second =
+                    second.hit(r, &mut Interval::new(ray_t.min, if hit_first { rec.t } else { ray_t.max }), rec);
+
+                hit_first || hit_second
+            }
+        }
+    }
+}
+
+// Object with the bounds used during the build
+struct Primitive {
+    object: Arc<dyn Hittable>,
+    bbox: AABB,
+    centroid: Point3,
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    bbox: AABB,
+    count: usize,
+}
+
+// Splits deeper than this use the median instead of the SAH. Median splits halve the
+// object count, which keeps any tree within the traversal stack of the flattened BVH
+const MAX_SAH_DEPTH: usize = 32;
+const STACK_SIZE: usize = 64;
+
+// Pointer based tree as built, every node a separate allocation. BVH flattens it for
+// rendering, the tree itself is kept for comparisons
+pub struct BvhTree {
+    root: BvhNode,
+    unbounded: Vec<Arc<dyn Hittable>>,
+    stats: BvhStats,
+}
+
+impl BvhTree {
     pub fn new(objects: &mut [Arc<dyn Hittable>]) -> Self {
-        let len = objects.len();
+        BvhTree::with_options(objects, &BvhOptions::new())
+    }
+
+    pub fn with_options(objects: &mut [Arc<dyn Hittable>], options: &BvhOptions) -> Self {
+        // Planes and other objects without finite bounds would inflate every box above them
+        let mut primitives: Vec<Primitive> = Vec::with_capacity(objects.len());
+        let mut unbounded: Vec<Arc<dyn Hittable>> = Vec::new();
+        for object in objects.iter() {
+            let bbox = object.bounding_box();
+            if bbox.is_bounded() {
+                primitives.push(Primitive { object: object.clone(), bbox, centroid: bbox.centroid() });
+            } else {
+                unbounded.push(object.clone());
+            }
+        }
+
+        let options = BvhOptions { max_leaf_size: options.max_leaf_size.max(1), bins: options.bins.max(2), ..*options };
+        let root = BvhTree::build(&mut primitives, 1, &options);
 
-        // Building the BBOX from the object span
+        let mut stats = BvhStats { unbounded_count: unbounded.len(), ..BvhStats::default() };
+        BvhTree::collect_stats(&root, 1, root.bbox().surface_area(), &options, &mut stats);
+        debug!(
+            "BVH over {} objects: {} nodes, {} leaves, depth {}, SAH cost {:.2}, {} unbounded",
+            stats.object_count, stats.node_count, stats.leaf_count, stats.depth, stats.sah_cost, stats.unbounded_count
+        );
+
+        BvhTree { root, unbounded, stats }
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.stats
+    }
+
+    fn build(primitives: &mut [Primitive], depth: usize, options: &BvhOptions) -> BvhNode {
         let mut bbox = AABB::EMPTY;
-        for i in 0..len {
-            bbox = AABB::from_bboxes(&bbox, &objects[i].bounding_box())
+        let mut centroid_bounds = AABB::EMPTY;
+        for primitive in primitives.iter() {
+            bbox = AABB::from_bboxes(&bbox, &primitive.bbox);
+            let centroid = AABB::from_points(primitive.centroid, primitive.centroid);
+            centroid_bounds = AABB::from_bboxes(&centroid_bounds, &centroid);
         }
 
-        let axis = bbox.longest_axis();
+        let count = primitives.len();
+        let leaf_cost = options.intersection_cost * count as f64;
+        let split = if count > 1 && depth < MAX_SAH_DEPTH {
+            BvhTree::best_split(primitives, &bbox, &centroid_bounds, options)
+        } else {
+            None
+        };
+
+        let mut partition = None;
+        if let Some((cost, axis, split_bin)) = split {
+            if count > options.max_leaf_size || cost < leaf_cost {
+                let extent = centroid_bounds.axis_interval(axis);
+                let mut mid = 0;
+                for i in 0..count {
+                    if bin_index(primitives[i].centroid[axis], &extent, options.bins) < split_bin {
+                        primitives.swap(i, mid);
+                        mid += 1;
+                    }
+                }
+                partition = Some((axis, mid));
+            }
+        }
 
-        let comparator = match axis {
-            0 => BVH::box_x_compare,
-            1 => BVH::box_y_compare,
-            _ => BVH::box_z_compare,
+        let (axis, mid) = match partition {
+            Some((axis, mid)) if mid > 0 && mid < count => (axis, mid),
+            // No plane separates the centroids, they all sit in the same spot, or the tree is too deep
+            _ if count > options.max_leaf_size => {
+                let axis = bbox.longest_axis() as usize;
+                primitives.sort_by(|a, b| a.bbox.axis_interval(axis).min.total_cmp(&b.bbox.axis_interval(axis).min));
+                (axis, count / 2)
+            }
+            _ => {
+                let objects = primitives.iter().map(|p| p.object.clone()).collect();
+                return BvhNode::Leaf { bbox, objects };
+            }
         };
 
-        let object_span = len;
+        let (left, right) = primitives.split_at_mut(mid);
+        let children = Box::new([BvhTree::build(left, depth + 1, options), BvhTree::build(right, depth + 1, options)]);
+        BvhNode::Interior { bbox, axis, children }
+    }
 
-        let left: Arc<dyn Hittable>;
-        let right: Arc<dyn Hittable>;
-        if object_span == 1 {
-            left = objects[0].clone();
-            right = objects[0].clone();
-        } else if object_span == 2 {
-            left = objects[0].clone();
-            right = objects[1].clone();
-        } else {
-            objects.sort_by(|a, b| comparator(a, b).cmp(&comparator(b, a))); 
+    // Cheapest split over the binned centroids of every axis, as (cost, axis, first bin on the right)
+    fn best_split(
+        primitives: &[Primitive],
+        bbox: &AABB,
+        centroid_bounds: &AABB,
+        options: &BvhOptions,
+    ) -> Option<(f64, usize, usize)> {
+        let area = bbox.surface_area();
+        let bin_count = options.bins;
+        let mut best: Option<(f64, usize, usize)> = None;
+
+        for axis in 0..3 {
+            let extent = centroid_bounds.axis_interval(axis);
+            if extent.size() <= 0.0 {
+                continue;
+            }
 
-            let mid = object_span / 2;
-            left = Arc::new(BVH::new(&mut objects[0..mid]));
-            right = Arc::new(BVH::new(&mut objects[mid..len]));
+            let mut bins = vec![Bin { bbox: AABB::EMPTY, count: 0 }; bin_count];
+            for primitive in primitives {
+                let bin = &mut bins[bin_index(primitive.centroid[axis], &extent, bin_count)];
+                bin.bbox = AABB::from_bboxes(&bin.bbox, &primitive.bbox);
+                bin.count += 1;
+            }
+
+            // Area times count of everything right of each plane, swept from the right
+            let mut right_cost = vec![0.0; bin_count];
+            let mut right = Bin { bbox: AABB::EMPTY, count: 0 };
+            for i in (1..bin_count).rev() {
+                right.bbox = AABB::from_bboxes(&right.bbox, &bins[i].bbox);
+                right.count += bins[i].count;
+                right_cost[i] = right.bbox.surface_area() * right.count as f64;
+            }
+
+            let mut left = Bin { bbox: AABB::EMPTY, count: 0 };
+            for i in 1..bin_count {
+                left.bbox = AABB::from_bboxes(&left.bbox, &bins[i - 1].bbox);
+                left.count += bins[i - 1].count;
+                if left.count == 0 || left.count == primitives.len() {
+                    continue;
+                }
+
+                let cost = options.traversal_cost
+                    + options.intersection_cost * (left.bbox.surface_area() * left.count as f64 + right_cost[i]) / area;
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, i));
+                }
+            }
         }
 
-        BVH {
-            left: left,
-            right: right,
-            bbox: bbox,
+        best
+    }
+
+    fn collect_stats(node: &BvhNode, depth: usize, root_area: f64, options: &BvhOptions, stats: &mut BvhStats) {
+        // Chance of a ray through the root also crossing this node
+        let probability = if root_area > 0.0 { node.bbox().surface_area() / root_area } else { 1.0 };
+
+        stats.node_count += 1;
+        stats.depth = stats.depth.max(depth);
+        match node {
+            BvhNode::Leaf { objects, .. } => {
+                stats.leaf_count += 1;
+                stats.object_count += objects.len();
+                stats.largest_leaf = stats.largest_leaf.max(objects.len());
+                stats.sah_cost += probability * options.intersection_cost * objects.len() as f64;
+            }
+            BvhNode::Interior { children, .. } => {
+                stats.sah_cost += probability * options.traversal_cost;
+                for child in children.iter() {
+                    BvhTree::collect_stats(child, depth + 1, root_area, options, stats);
+                }
+            }
         }
     }
+}
 
-    fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis_index: usize) -> bool {
-        let a_axis_interval = a.bounding_box().axis_interval(axis_index);
-        let b_axis_interval = b.bounding_box().axis_interval(axis_index);
+// Bin of a centroid coordinate within the centroid extent along one axis
+fn bin_index(c: f64, extent: &Interval, bin_count: usize) -> usize {
+    (((c - extent.min) / extent.size() * bin_count as f64) as usize).min(bin_count - 1)
+}
 
-        let res = a_axis_interval.min < b_axis_interval.min;
-        return res;
+impl Hittable for BvhTree {
+    fn hit(&self, r: &Ray, ray_t: &mut Interval, rec: &mut HitRecord) -> bool {
+        let hit_tree = self.root.hit(r, ray_t, rec);
+        let closest = if hit_tree { rec.t } else { ray_t.max };
+        hit_unbounded(&self.unbounded, r, Interval::new(ray_t.min, closest), rec) || hit_tree
     }
 
-    fn box_x_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> bool {
-        BVH::box_compare(a, b, 0)
+    fn bounding_box(&self) -> AABB {
+        unbounded_box(*self.root.bbox(), &self.unbounded)
     }
+}
 
-    fn box_y_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> bool {
-        BVH::box_compare(a, b, 1)
+fn hit_unbounded(objects: &[Arc<dyn Hittable>], r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+    let mut hit_anything = false;
+    let mut closest = ray_t.max;
+    for object in objects {
+        if object.hit(r, &mut Interval::new(ray_t.min, closest), rec) {
+            hit_anything = true;
+            closest = rec.t;
+        }
     }
+    hit_anything
+}
 
-    fn box_z_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> bool {
-        BVH::box_compare(a, b, 2)
+// Box of the tree grown by the unbounded objects, infinite as soon as there is one
+fn unbounded_box(bbox: AABB, objects: &[Arc<dyn Hittable>]) -> AABB {
+    objects.iter().fold(bbox, |bbox, object| AABB::from_bboxes(&bbox, &object.bounding_box()))
+}
+
+// Node of the flattened tree, stored depth first so the first child of an interior node
+// directly follows it
+#[derive(Clone, Copy)]
+struct LinearNode {
+    bbox: AABB,
+    // First object of a leaf, or the index of the second child of an interior node
+    offset: u32,
+    // Objects in a leaf, 0 for interior nodes
+    count: u32,
+    axis: u8,
+}
+
+// Bounding volume hierarchy in one array, traversed with an explicit stack
+pub struct BVH {
+    nodes: Vec<LinearNode>,
+    // Objects of all leaves, each leaf a contiguous range
+    objects: Vec<Arc<dyn Hittable>>,
+    unbounded: Vec<Arc<dyn Hittable>>,
+    stats: BvhStats,
+}
+
+impl BVH {
+    pub fn from_hittable(world: HittableList) -> Self {
+        BVH::new(&mut world.list.clone())
+    }
+
+    pub fn new(objects: &mut [Arc<dyn Hittable>]) -> Self {
+        BVH::with_options(objects, &BvhOptions::new())
+    }
+
+    pub fn with_options(objects: &mut [Arc<dyn Hittable>], options: &BvhOptions) -> Self {
+        BVH::from_tree(BvhTree::with_options(objects, options))
+    }
+
+    pub fn from_tree(tree: BvhTree) -> Self {
+        let mut bvh = BVH {
+            nodes: Vec::with_capacity(tree.stats.node_count),
+            objects: Vec::with_capacity(tree.stats.object_count),
+            unbounded: tree.unbounded,
+            stats: tree.stats,
+        };
+        // An empty tree flattens to no nodes at all
+        if tree.stats.object_count > 0 {
+            bvh.flatten(tree.root);
+        }
+        bvh
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.stats
+    }
+
+    // Calls visit with every object in a leaf the ray passes through within ray_t, and with
+    // the unbounded ones. For gathering every hit along a ray rather than the closest
+    pub fn for_each_along(&self, r: &Ray, ray_t: Interval, mut visit: impl FnMut(&dyn Hittable)) {
+        if !self.nodes.is_empty() {
+            let origin = r.origin();
+            let direction = r.direction();
+            let inv_dir = Vec3::new(1.0 / direction[0], 1.0 / direction[1], 1.0 / direction[2]);
+
+            let mut stack = [0u32; STACK_SIZE];
+            let mut top = 0;
+            let mut index = 0;
+            loop {
+                let node = &self.nodes[index];
+                if node.bbox.hit_inverse(origin, inv_dir, ray_t) {
+                    if node.count > 0 {
+                        let start = node.offset as usize;
+                        self.objects[start..start + node.count as usize].iter().for_each(|object| visit(object.as_ref()));
+                    } else {
+                        // The first child follows its parent, the second one is at offset
+                        stack[top] = node.offset;
+                        top += 1;
+                        index += 1;
+                        continue;
+                    }
+                }
+
+                if top == 0 {
+                    break;
+                }
+                top -= 1;
+                index = stack[top] as usize;
+            }
+        }
+
+        self.unbounded.iter().for_each(|object| visit(object.as_ref()));
+    }
+
+    fn flatten(&mut self, node: BvhNode) {
+        match node {
+            BvhNode::Leaf { bbox, objects } => {
+                let offset = self.objects.len() as u32;
+                self.nodes.push(LinearNode { bbox, offset, count: objects.len() as u32, axis: 0 });
+                self.objects.extend(objects);
+            }
+            BvhNode::Interior { bbox, axis, children } => {
+                let index = self.nodes.len();
+                self.nodes.push(LinearNode { bbox, offset: 0, count: 0, axis: axis as u8 });
+
+                let [first, second] = *children;
+                self.flatten(first);
+                self.nodes[index].offset = self.nodes.len() as u32;
+                self.flatten(second);
+            }
+        }
     }
 }
 
 impl Hittable for BVH {
-    fn hit(
-        &self,
-        r: &crate::ray::Ray,
-        ray_t: &mut crate::interval::Interval,
-        rec: &mut crate::hittable::HitRecord,
-    ) -> bool {
-        // Exit early if bbox of this node is not hit
-        if !self.bbox.hit(r, *ray_t) {
-            return false;
+    fn hit(&self, r: &Ray, ray_t: &mut Interval, rec: &mut HitRecord) -> bool {
+        if self.nodes.is_empty() {
+            return hit_unbounded(&self.unbounded, r, *ray_t, rec);
         }
 
-        let hit_left = self.left.hit(r, ray_t, rec);
-        let hit_right = self.right.hit(r, &mut Interval::new(ray_t.min, if hit_left {rec.t} else {ray_t.max}), rec);
+        let mut hit_anything = false;
+        let mut closest = ray_t.max;
+
+        let origin = r.origin();
+        let direction = r.direction();
+        let inv_dir = Vec3::new(1.0 / direction[0], 1.0 / direction[1], 1.0 / direction[2]);
+
+        // Far children waiting to be visited
+        let mut stack = [0u32; STACK_SIZE];
+        let mut top = 0;
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            if node.bbox.hit_inverse(origin, inv_dir, Interval::new(ray_t.min, closest)) {
+                if node.count > 0 {
+                    let start = node.offset as usize;
+                    for object in &self.objects[start..start + node.count as usize] {
+                        if object.hit(r, &mut Interval::new(ray_t.min, closest), rec) {
+                            hit_anything = true;
+                            closest = rec.t;
+                        }
+                    }
+                } else {
+                    // Near child first, so the far one is often culled by the shortened interval
+                    let (near, far) = if direction[node.axis as usize] < 0.0 {
+                        (node.offset as usize, index as u32 + 1)
+                    } else {
+                        (index + 1, node.offset)
+                    };
+                    stack[top] = far;
+                    top += 1;
+                    index = near;
+                    continue;
+                }
+            }
+
+            if top == 0 {
+                break;
+            }
+            top -= 1;
+            index = stack[top] as usize;
+        }
 
-        hit_left || hit_right
+        hit_unbounded(&self.unbounded, r, Interval::new(ray_t.min, closest), rec) || hit_anything
     }
 
     fn bounding_box(&self) -> AABB {
-        self.bbox
+        unbounded_box(self.nodes.first().map_or(AABB::EMPTY, |node| node.bbox), &self.unbounded)
     }
 }
@@ -1,45 +1,106 @@
+use std::io;
+use std::sync::Arc;
+
 use log::info;
 use rayon::prelude::*;
 
 use crate::{
-    color::{write_color, Color},
-    hittable::HitRecord,
+    camera_model::{
+        CameraFrame, CameraModel, CameraSample, EquirectangularCamera, FisheyeCamera, OrthographicCamera,
+        PerspectiveCamera,
+    },
+    color::{luminance, Color},
+    environment::EnvironmentLight,
+    framebuffer::Framebuffer,
+    hittable::{HitRecord, Hittable},
     hittable_list::HittableList,
-    hittable::Hittable,
+    integrator::{Integrator, PathIntegrator, RenderContext},
     interval::Interval,
+    physical_camera::PhysicalCamera,
     ray::Ray,
-    utils::{degrees_to_radians, random_uniform, INFINITY},
-    vec3::{cross, random_unit_disk, unit_vector, Point3, Vec3},
+    sampler::{Sampler, SamplerType},
+    shutter::ShutterCurve,
+    utils::{degrees_to_radians, INFINITY},
+    vec3::{dot, random_unit_disk, unit_vector, Point3, Vec3},
 };
+
+// Part of the Projection settings, so it stays reachable from here
+pub use crate::camera_model::FisheyeMapping;
+
+// How the viewport is mapped onto rays
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // Rays spread from lookfrom through the viewport, covering vfov vertically
+    Perspective,
+    // Parallel rays along the view direction from a viewport ortho_height tall,
+    // so sizes don't shrink with distance
+    Orthographic,
+    // Full 360 by 180 latitude-longitude panorama from lookfrom, with lookat in the
+    // middle of the image and vup at the top. Made for a 2:1 image
+    Equirectangular,
+    // Circular image of a fisheye_fov wide cone around the view direction, fitting the
+    // shorter side of the image. Pixels outside the circle stay black
+    Fisheye(FisheyeMapping),
+}
+
 pub struct Camera {
     pub aspect_ratio: f64,
     pub image_width: i32,
     pub samples_per_pixel: i32,
     pub max_depth: i32,
     
+    pub projection: Projection,
     pub vfov: f64, // Field of View Angle
+    pub ortho_height: f64,
+    pub fisheye_fov: f64, // Degrees, up to 360
     pub lookfrom: Point3,
     pub lookat: Point3,
     pub vup: Vec3,
+    // Takes the place of the projection settings above, for cameras built outside of them
+    pub model: Option<Arc<dyn CameraModel>>,
 
     pub defocus_angle: f64,
     pub focus_dist: f64,
+    // Auto-focus. Before rendering, focus_dist is moved to the depth of whatever the center
+    // of this pixel sees, keeping the lens the same size
+    pub focus_pixel: Option<(i32, i32)>,
     pub background: Color,
+    // Scales the radiance of every camera ray, see PhysicalCamera::exposure
+    pub exposure: f64,
+
+    // Rays carry times from shutter_open to shutter_close, in the same time as the
+    // motion of the scene, spread over the interval by the shutter curve
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    pub shutter_curve: ShutterCurve,
+
+    // Seed for all random decisions. Same seed and scene give the same image
+    pub seed: u64,
+    // Sequence used for pixel jitter, lens, time and scattering decisions
+    pub sampler: SamplerType,
+
+    // Adaptive sampling. Pixels are sampled in passes until the relative
+    // standard error of their luminance falls under adaptive_threshold, taking
+    // between min_samples_per_pixel and samples_per_pixel samples
+    pub adaptive_sampling: bool,
+    pub adaptive_threshold: f64,
+    pub min_samples_per_pixel: i32,
+    pub samples_per_pass: i32,
+
+    // Bounces taken before paths may be terminated by Russian roulette
+    pub russian_roulette_depth: i32,
+
+    // Environment map lighting the scene from every direction, replaces the background
+    pub environment: Option<Arc<EnvironmentLight>>,
+
+    // Light transport algorithm run for every camera ray
+    pub integrator: Arc<dyn Integrator>,
+
+    // Emitters sampled directly at every diffuse bounce. They must also be part of the world
+    pub lights: HittableList,
 
-    initialized: bool,
     image_height: i32,
     pixel_sample_scale: f64,
-    center: Point3,
-    pixel_00_loc: Point3,
-    pixel_delta_u: Vec3,
-    pixel_delta_v: Vec3,
-    // Camera basis vectors
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
-    // Focus blur
-    defocus_disk_u: Vec3,
-    defocus_disk_v: Vec3,
 }
 
 impl Camera {
@@ -49,143 +110,281 @@ impl Camera {
             image_width: 0,
             samples_per_pixel: 5,
             max_depth: 10,
+            projection: Projection::Perspective,
             vfov: 90.0,
+            ortho_height: 2.0,
+            fisheye_fov: 180.0,
             lookfrom: Point3::empty(),
             lookat: Point3::new(0.0, 0.0, -1.0),
             vup: Vec3::new(0.0, 1.0, 0.0),
+            model: None,
             defocus_angle: 0.0,
             focus_dist: 10.0,
+            focus_pixel: None,
             background: Color::empty(),
+            exposure: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            shutter_curve: ShutterCurve::Box,
+            seed: 0,
+            sampler: SamplerType::Independent,
+            adaptive_sampling: false,
+            adaptive_threshold: 0.01,
+            min_samples_per_pixel: 16,
+            samples_per_pass: 16,
+            russian_roulette_depth: 3,
+            environment: None,
+            integrator: Arc::new(PathIntegrator),
+            lights: HittableList::new(),
             image_height: 0,
             pixel_sample_scale: 1.0/5.0,
-            center: Point3::empty(),
-            pixel_00_loc: Point3::empty(),
-            pixel_delta_u: Point3::empty(),
-            pixel_delta_v: Point3::empty(),
-            u: Vec3::empty(),
-            v: Vec3::empty(),
-            w: Vec3::empty(),
-            defocus_disk_u: Vec3::empty(),
-            defocus_disk_v: Vec3::empty(),
-            initialized: false,
         }
     }
 
+    // Camera set up from photographic settings, with everything else at its default
+    pub fn from_physical(settings: &PhysicalCamera) -> Result<Self, String> {
+        let mut cam = Camera::new();
+        settings.apply(&mut cam)?;
+        Ok(cam)
+    }
+
     fn initialize(&mut self) {
-        self.image_height = (self.image_width as f64 / self.aspect_ratio) as i32;
+        self.image_height = ((self.image_width as f64 / self.aspect_ratio) as i32).max(1);
 
         self.pixel_sample_scale = 1.0 / self.samples_per_pixel as f64;
-
-        self.center = self.lookfrom;
-
-        let theta = degrees_to_radians(self.vfov);
-        let h = f64::tan(theta/2.0);
-        let viewport_height = 2.0 * h * self.focus_dist;
-        let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64);
-
-        // Calculate u, v, w camera basis vectors
-        self.w = unit_vector(self.lookfrom - self.lookat);
-        self.u = unit_vector(cross(self.vup, self.w));
-        self.v = cross(self.w, self.u);
-
-        // Viewport managing
-        let viewport_u = viewport_width * self.u;
-        let viewport_v = viewport_height * -self.v;
-
-        self.pixel_delta_u = viewport_u / self.image_width as f64;
-        self.pixel_delta_v = viewport_v / self.image_height as f64;
-
-        let viewport_upper_left =
-            self.center - self.focus_dist * self.w - viewport_u / 2.0 - viewport_v / 2.0;
-        self.pixel_00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
-
-        // Focus disk basis vectors
-        let defocus_radius = self.focus_dist * f64::tan(degrees_to_radians(self.defocus_angle / 2.0));
-        self.defocus_disk_u = self.u * defocus_radius;
-        self.defocus_disk_v = self.v * defocus_radius;
-
-        self.initialized = true;
     }
 
-    fn ray_color(&self, r: &Ray, world: &HittableList, depth: i32) -> Color {
-        if depth <= 0 {
-            return Color::empty();
+    fn auto_focus(&mut self, world: &HittableList) {
+        let Some((i, j)) = self.focus_pixel else {
+            return;
+        };
+        // The focus depth is measured along lookfrom to lookat, which a given model need not follow
+        if self.model.is_some() {
+            info!("Auto-focus skipped, the camera has a custom model");
+            return;
+        }
+        if !(0..self.image_width).contains(&i) || !(0..self.image_height).contains(&j) {
+            info!("Auto-focus pixel ({}, {}) is outside the image, keeping focus at {}", i, j, self.focus_dist);
+            return;
         }
 
+        let film = ((i as f64 + 0.5) / self.image_width as f64, (j as f64 + 0.5) / self.image_height as f64);
+        let time = 0.5 * (self.shutter_open + self.shutter_close);
+        let Some(r) = self.camera_model().generate_ray(&CameraSample { film, lens: (0.0, 0.0), time }) else {
+            info!("Auto-focus pixel ({}, {}) sees nothing, keeping focus at {}", i, j, self.focus_dist);
+            return;
+        };
         let mut rec = HitRecord::new();
-        if !world.hit(r, &mut Interval::new(0.001, INFINITY), &mut rec) {
-            return self.background;
+        if !world.hit(&r, &mut Interval::new(0.001, INFINITY), &mut rec) {
+            info!("Auto-focus ray through ({}, {}) missed, keeping focus at {}", i, j, self.focus_dist);
+            return;
         }
 
-        let mut scattered = Ray::new(Point3::empty(), Vec3::empty());
-        let mut attenuation = Color::empty();
-        let color_from_emission = rec.mat.emitted(rec.u, rec.v, rec.p);
+        // The focus plane faces the camera, so it goes through the hit at its depth along the view
+        let depth = dot(self.lookfrom - rec.p, unit_vector(self.lookfrom - self.lookat));
+        if depth <= 0.0 {
+            return;
+        }
+        if self.defocus_angle > 0.0 {
+            let radius = self.focus_dist * f64::tan(degrees_to_radians(self.defocus_angle / 2.0));
+            self.defocus_angle = 2.0 * f64::atan(radius / depth).to_degrees();
+        }
+        self.focus_dist = depth;
+        info!("Auto-focus at {}", depth);
+    }
 
-        if !rec.mat.scatter(r, &rec, &mut attenuation, &mut scattered) {
-            return color_from_emission;
+    // The model rays are generated with, from the settings unless one was given
+    fn camera_model(&self) -> Arc<dyn CameraModel> {
+        if let Some(model) = &self.model {
+            return model.clone();
         }
 
-        let color_from_scatter = attenuation * self.ray_color(&scattered, world, depth-1);
-        color_from_emission + color_from_scatter
+        let frame = CameraFrame::look_at(self.lookfrom, self.lookat, self.vup);
+        let aspect_ratio = self.image_width as f64 / self.image_height as f64;
+        match self.projection {
+            Projection::Perspective => {
+                Arc::new(PerspectiveCamera::new(frame, self.vfov, aspect_ratio, self.focus_dist, self.defocus_angle))
+            }
+            Projection::Orthographic => Arc::new(OrthographicCamera::new(
+                frame,
+                self.ortho_height,
+                aspect_ratio,
+                self.focus_dist,
+                self.defocus_angle,
+            )),
+            Projection::Equirectangular => Arc::new(EquirectangularCamera::new(frame)),
+            Projection::Fisheye(mapping) => Arc::new(FisheyeCamera::new(frame, self.fisheye_fov, mapping, aspect_ratio)),
+        }
     }
 
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
-        let offset = self.sample_square();
-        let pixel_sample = self.pixel_00_loc + ((i as f64 + offset.x()) * self.pixel_delta_u) + ((j as f64 + offset.y()) * self.pixel_delta_v);
-    
-        let origin = if self.defocus_angle <= 0.0 {self.center} else {self.defocus_disk_sample()};
-        let direction = pixel_sample - origin;
-        let ray_time = random_uniform();
+    // None for pixels the model doesn't cover
+    fn get_ray(&self, model: &dyn CameraModel, i: i32, j: i32, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let offset = self.sample_square(sampler);
+        let film = (
+            (i as f64 + 0.5 + offset.x()) / self.image_width as f64,
+            (j as f64 + 0.5 + offset.y()) / self.image_height as f64,
+        );
 
-        Ray::new_with_time(origin, direction, ray_time)
+        // The lens is always drawn so the sampler layout doesn't depend on the model
+        let lens = random_unit_disk(sampler);
+        let shutter = self.shutter_curve.sample(sampler.get_1d());
+        let time = self.shutter_open + shutter * (self.shutter_close - self.shutter_open);
+
+        model.generate_ray(&CameraSample { film, lens: (lens.x(), lens.y()), time })
     }
 
-    fn sample_square(&self) -> Vec3 {
-        Vec3::new(random_uniform() - 0.5, random_uniform() - 0.5, 0.0)
+    fn sample_square(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let (x, y) = sampler.get_2d();
+        Vec3::new(x - 0.5, y - 0.5, 0.0)
     }
 
-    fn defocus_disk_sample(&self) -> Vec3 {
-        let p = random_unit_disk();
-        self.center + (p.x() * self.defocus_disk_u) + (p.y() * self.defocus_disk_v)
+    fn sample_pixel(&self, model: &dyn CameraModel, i: i32, j: i32, index: i32, world: &HittableList, sampler: &mut dyn Sampler) -> Color {
+        sampler.start_pixel_sample(i, j, index);
+        let Some(r) = self.get_ray(model, i, j, sampler) else {
+            return Color::empty();
+        };
+
+        let ctx = RenderContext {
+            world,
+            lights: &self.lights,
+            background: self.background,
+            environment: self.environment.as_deref(),
+            max_depth: self.max_depth,
+            russian_roulette_depth: self.russian_roulette_depth,
+        };
+        self.integrator.li(&r, &ctx, sampler) * self.exposure
     }
 
-    pub fn render(mut self, world: &HittableList) {
-        if !self.initialized {
-            self.initialize();
-        }
+    pub fn render_to_buffer(&mut self, world: &HittableList) -> Framebuffer {
+        // Settings may have changed since the last render
+        self.initialize();
+        self.auto_focus(world);
 
-        // Render
-        println!("P3\n{0} {1}\n255\n", self.image_width, self.image_height);
+        let model = self.camera_model();
+        let image = if self.adaptive_sampling {
+            self.render_adaptive(model.as_ref(), world)
+        } else {
+            self.render_uniform(model.as_ref(), world)
+        };
 
-            // Create a Vec of Vecs (dynamic 2D array)
-        let mut pixels: Vec<Vec<Point3>> = Vec::with_capacity(self.image_height as usize);
+        info!("Done!");
+        image
+    }
 
-        // Initialize each row
-        for _ in 0..self.image_height {
-            let row = vec![Default::default(); self.image_width as usize];
-            pixels.push(row);
-        }
+    fn render_uniform(&self, model: &dyn CameraModel, world: &HittableList) -> Framebuffer {
+        let width = self.image_width as usize;
+        let mut image = Framebuffer::new(width, self.image_height as usize);
 
         // Parallelize the outer loop (over scanlines/rows)
-        pixels.par_iter_mut().enumerate().for_each(|(j, row)| { 
+        image.pixels_mut().par_chunks_mut(width).enumerate().for_each(|(j, row)| {
             info!("Scanline number: {}", j);
+            let mut sampler = self.sampler.build(self.samples_per_pixel, self.seed);
             for i in 0..self.image_width {
                 let mut pixel_color = Color::empty();
-                for _ in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j as i32);
-                    pixel_color = pixel_color + self.ray_color(&r, world, self.max_depth);
+                for s in 0..self.samples_per_pixel {
+                    pixel_color = pixel_color + self.sample_pixel(model, i, j as i32, s, world, sampler.as_mut());
                 }
-                row[i as usize] = pixel_color; // Assuming you have a conversion method
+                row[i as usize] = pixel_color * self.pixel_sample_scale;
             }
         });
-        
-        // Writing to stdout
-        for row in &pixels {
-            for pixel in row {
-                write_color(&(*pixel*self.pixel_sample_scale));
+
+        image.sample_counts_mut().fill(self.samples_per_pixel as u32);
+        image
+    }
+
+    fn render_adaptive(&self, model: &dyn CameraModel, world: &HittableList) -> Framebuffer {
+        let width = self.image_width as usize;
+        let height = self.image_height as usize;
+        let max_samples = self.samples_per_pixel.max(1);
+        let min_samples = self.min_samples_per_pixel.clamp(1, max_samples);
+        let pass_samples = self.samples_per_pass.max(1);
+
+        let mut stats = vec![PixelStats::new(); width * height];
+        let mut pass = 0;
+
+        loop {
+            stats.par_chunks_mut(width).enumerate().for_each(|(j, row)| {
+                let mut sampler = self.sampler.build(max_samples, self.seed);
+                for (i, pixel) in row.iter_mut().enumerate() {
+                    if pixel.converged {
+                        continue;
+                    }
+
+                    let target = if pixel.count == 0 { min_samples } else { (pixel.count + pass_samples).min(max_samples) };
+                    while pixel.count < target {
+                        let color = self.sample_pixel(model, i as i32, j as i32, pixel.count, world, sampler.as_mut());
+                        pixel.add(color);
+                    }
+
+                    pixel.converged = pixel.count >= max_samples || pixel.relative_error() <= self.adaptive_threshold;
+                }
+            });
+
+            let active = stats.iter().filter(|p| !p.converged).count();
+            info!("Adaptive pass {}: {} pixels still sampling", pass, active);
+            pass += 1;
+
+            if active == 0 {
+                break;
             }
         }
 
-        info!("Done!");
+        let mut image = Framebuffer::from_pixels(width, height, stats.iter().map(|p| p.mean_color()).collect());
+        for (count, pixel) in image.sample_counts_mut().iter_mut().zip(&stats) {
+            *count = pixel.count as u32;
+        }
+        image
+    }
+
+    pub fn render(mut self, world: &HittableList) {
+        let image = self.render_to_buffer(world);
+
+        // Writing to stdout
+        let stdout = io::stdout();
+        image.write_ppm(&mut stdout.lock()).expect("Could not write image to stdout");
+    }
+}
+
+// Running statistics of the samples of one pixel, with the luminance variance
+// tracked through Welford's algorithm
+#[derive(Clone, Copy)]
+struct PixelStats {
+    sum: Color,
+    count: i32,
+    mean: f64,
+    m2: f64,
+    converged: bool,
+}
+
+impl PixelStats {
+    // Keeps the relative error finite on black pixels
+    const MIN_LUMINANCE: f64 = 0.01;
+
+    fn new() -> Self {
+        PixelStats { sum: Color::empty(), count: 0, mean: 0.0, m2: 0.0, converged: false }
+    }
+
+    fn add(&mut self, color: Color) {
+        self.sum = self.sum + color;
+        self.count += 1;
+
+        let y = luminance(&color);
+        let delta = y - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (y - self.mean);
+    }
+
+    // Standard error of the mean luminance, relative to the luminance itself
+    fn relative_error(&self) -> f64 {
+        if self.count < 2 {
+            return INFINITY;
+        }
+
+        let variance = self.m2 / (self.count - 1) as f64;
+        f64::sqrt(variance / self.count as f64) / f64::max(self.mean, PixelStats::MIN_LUMINANCE)
+    }
+
+    fn mean_color(&self) -> Color {
+        if self.count == 0 { Color::empty() } else { self.sum / self.count as f64 }
     }
 }
@@ -0,0 +1,201 @@
+use crate::ray::Ray;
+use crate::utils::{degrees_to_radians, PI};
+use crate::vec3::{cross, unit_vector, Point3, Vec3};
+
+// Position and orientation of a camera at center, looking down -w with u to the right
+// and v up
+#[derive(Clone, Copy, Debug)]
+pub struct CameraFrame {
+    pub center: Point3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl CameraFrame {
+    pub fn look_at(lookfrom: Point3, lookat: Point3, vup: Vec3) -> Self {
+        let w = unit_vector(lookfrom - lookat);
+        let u = unit_vector(cross(vup, w));
+        let v = cross(w, u);
+        CameraFrame { center: lookfrom, u, v, w }
+    }
+}
+
+// What the renderer hands a camera model for one ray
+#[derive(Clone, Copy, Debug)]
+pub struct CameraSample {
+    // Position on the film, from (0, 0) at the top left corner to (1, 1) at the bottom right
+    pub film: (f64, f64),
+    // Point on the unit disk, for cameras with a lens
+    pub lens: (f64, f64),
+    pub time: f64,
+}
+
+// Turns film positions into rays. Pixels, the shutter and integration are left to the
+// renderer, so this is all a new kind of camera has to provide
+pub trait CameraModel: Sync + Send {
+    // None where the camera sees nothing, like outside a fisheye circle
+    fn generate_ray(&self, sample: &CameraSample) -> Option<Ray>;
+}
+
+// Film laid out on the focus plane, with a thin lens in front of it
+struct Viewport {
+    upper_left: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    defocus_disk_u: Vec3,
+    defocus_disk_v: Vec3,
+}
+
+impl Viewport {
+    fn new(frame: &CameraFrame, height: f64, aspect_ratio: f64, focus_dist: f64, defocus_angle: f64) -> Self {
+        let horizontal = height * aspect_ratio * frame.u;
+        let vertical = height * -frame.v;
+        let upper_left = frame.center - focus_dist * frame.w - horizontal / 2.0 - vertical / 2.0;
+
+        let defocus_radius = if defocus_angle <= 0.0 {
+            0.0
+        } else {
+            focus_dist * f64::tan(degrees_to_radians(defocus_angle / 2.0))
+        };
+
+        Viewport {
+            upper_left,
+            horizontal,
+            vertical,
+            defocus_disk_u: defocus_radius * frame.u,
+            defocus_disk_v: defocus_radius * frame.v,
+        }
+    }
+
+    fn point(&self, (x, y): (f64, f64)) -> Point3 {
+        self.upper_left + x * self.horizontal + y * self.vertical
+    }
+
+    // Offset from the middle of the lens
+    fn lens_offset(&self, (x, y): (f64, f64)) -> Vec3 {
+        x * self.defocus_disk_u + y * self.defocus_disk_v
+    }
+}
+
+// Pinhole or thin lens camera, with rays spreading from the center to cover vfov vertically
+pub struct PerspectiveCamera {
+    center: Point3,
+    viewport: Viewport,
+}
+
+impl PerspectiveCamera {
+    // aspect_ratio is the width of the film over its height
+    pub fn new(frame: CameraFrame, vfov: f64, aspect_ratio: f64, focus_dist: f64, defocus_angle: f64) -> Self {
+        let height = 2.0 * f64::tan(degrees_to_radians(vfov) / 2.0) * focus_dist;
+        PerspectiveCamera { center: frame.center, viewport: Viewport::new(&frame, height, aspect_ratio, focus_dist, defocus_angle) }
+    }
+}
+
+impl CameraModel for PerspectiveCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> Option<Ray> {
+        let origin = self.center + self.viewport.lens_offset(sample.lens);
+        Some(Ray::new_with_time(origin, self.viewport.point(sample.film) - origin, sample.time))
+    }
+}
+
+// Parallel rays along the view direction from a film height units tall, so sizes don't
+// shrink with distance
+pub struct OrthographicCamera {
+    viewport: Viewport,
+    // From the focus plane back to the plane of the camera
+    back: Vec3,
+}
+
+impl OrthographicCamera {
+    pub fn new(frame: CameraFrame, height: f64, aspect_ratio: f64, focus_dist: f64, defocus_angle: f64) -> Self {
+        OrthographicCamera {
+            viewport: Viewport::new(&frame, height, aspect_ratio, focus_dist, defocus_angle),
+            back: focus_dist * frame.w,
+        }
+    }
+}
+
+impl CameraModel for OrthographicCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> Option<Ray> {
+        // Straight back from the film onto the plane of the camera, shifted across the lens
+        let target = self.viewport.point(sample.film);
+        let origin = target + self.back + self.viewport.lens_offset(sample.lens);
+        Some(Ray::new_with_time(origin, target - origin, sample.time))
+    }
+}
+
+// Full 360 by 180 latitude-longitude panorama, with the view direction in the middle of
+// the film and v at the top. Made for a 2:1 film. A point has no lens to blur with
+pub struct EquirectangularCamera {
+    frame: CameraFrame,
+}
+
+impl EquirectangularCamera {
+    pub fn new(frame: CameraFrame) -> Self {
+        EquirectangularCamera { frame }
+    }
+}
+
+impl CameraModel for EquirectangularCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> Option<Ray> {
+        let (x, y) = sample.film;
+        let longitude = (x - 0.5) * 2.0 * PI;
+        let latitude = (0.5 - y) * PI;
+
+        let horizontal = longitude.sin() * self.frame.u - longitude.cos() * self.frame.w;
+        let direction = latitude.cos() * horizontal + latitude.sin() * self.frame.v;
+        Some(Ray::new_with_time(self.frame.center, direction, sample.time))
+    }
+}
+
+// How the angle from the view direction maps to the distance from the fisheye center
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FisheyeMapping {
+    // Distance proportional to the angle
+    #[default]
+    Equidistant,
+    // Equal solid angles take equal areas, distance proportional to sin(angle / 2)
+    Equisolid,
+}
+
+// Circular image of a fov degrees wide cone around the view direction, fitting the
+// shorter side of the film. Nothing is seen outside the circle
+pub struct FisheyeCamera {
+    frame: CameraFrame,
+    half_fov: f64,
+    mapping: FisheyeMapping,
+    aspect_ratio: f64,
+}
+
+impl FisheyeCamera {
+    // fov goes up to 360
+    pub fn new(frame: CameraFrame, fov: f64, mapping: FisheyeMapping, aspect_ratio: f64) -> Self {
+        FisheyeCamera { frame, half_fov: degrees_to_radians(fov) / 2.0, mapping, aspect_ratio }
+    }
+}
+
+impl CameraModel for FisheyeCamera {
+    fn generate_ray(&self, sample: &CameraSample) -> Option<Ray> {
+        // Position relative to the film center, 1 at the edge of the circle
+        let (x, y) = sample.film;
+        let (dx, dy) = if self.aspect_ratio >= 1.0 {
+            (2.0 * (x - 0.5) * self.aspect_ratio, 2.0 * (0.5 - y))
+        } else {
+            (2.0 * (x - 0.5), 2.0 * (0.5 - y) / self.aspect_ratio)
+        };
+        let r = f64::sqrt(dx * dx + dy * dy);
+        if r > 1.0 {
+            return None;
+        }
+
+        let theta = match self.mapping {
+            FisheyeMapping::Equidistant => r * self.half_fov,
+            FisheyeMapping::Equisolid => 2.0 * f64::asin(r * f64::sin(self.half_fov / 2.0)),
+        };
+        let phi = dy.atan2(dx);
+        let sideways = phi.cos() * self.frame.u + phi.sin() * self.frame.v;
+        let direction = theta.sin() * sideways - theta.cos() * self.frame.w;
+        Some(Ray::new_with_time(self.frame.center, direction, sample.time))
+    }
+}
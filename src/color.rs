@@ -16,7 +16,8 @@ fn linear_to_gamma(linear: f64) -> f64 {
     0.0
 }
 
-pub fn write_color(pixel_color: &Color) {
+// Gamma corrects a linear color and quantizes it to bytes
+pub fn color_to_bytes(pixel_color: &Color) -> [u8; 3] {
     let mut r = pixel_color.x();
     let mut g = pixel_color.y();
     let mut b = pixel_color.z();
@@ -31,5 +32,15 @@ pub fn write_color(pixel_color: &Color) {
     let gbyte = (256.0 * INTENSITY.clamp(g)) as u8;
     let bbyte = (256.0 * INTENSITY.clamp(b)) as u8;
 
+    [rbyte, gbyte, bbyte]
+}
+
+// Relative luminance of a linear Rec. 709 color
+pub fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
+
+pub fn write_color(pixel_color: &Color) {
+    let [rbyte, gbyte, bbyte] = color_to_bytes(pixel_color);
     println!("{rbyte} {gbyte} {bbyte}");
 }
\ No newline at end of file
@@ -6,7 +6,8 @@ use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::material::{Isotropic, Material};
 use crate::texture::Texture;
-use crate::utils::{random_uniform, INFINITY};
+use crate::ray::Ray;
+use crate::utils::{hash, random_uniform, Rng, INFINITY};
 use crate::vec3::Vec3;
 
 pub struct ConstantMedium {
@@ -35,12 +36,22 @@ impl ConstantMedium {
             phase_function: Arc::new(Isotropic::from_color(albedo)),
         }
     }
+
+    fn ray_seed(r: &Ray) -> u64 {
+        let o = r.origin();
+        let d = r.direction();
+        hash(&[
+            o.x().to_bits(), o.y().to_bits(), o.z().to_bits(),
+            d.x().to_bits(), d.y().to_bits(), d.z().to_bits(),
+            r.time().to_bits(),
+        ])
+    }
 }
 
 impl Hittable for ConstantMedium {
     fn hit(
         &self,
-        r: &crate::ray::Ray,
+        r: &Ray,
         ray_t: &mut Interval,
         rec: &mut HitRecord,
     ) -> bool {
@@ -77,7 +88,10 @@ impl Hittable for ConstantMedium {
 
         let ray_length = r.direction().length();
         let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-        let hit_distance = self.neg_inv_density * random_uniform().ln();
+        // Hit tests don't carry an Rng, so the free-flight distance comes from a
+        // stream seeded by the ray itself. This keeps renders reproducible
+        let mut rng = Rng::new(ConstantMedium::ray_seed(r));
+        let hit_distance = self.neg_inv_density * random_uniform(&mut rng).ln();
 
         if hit_distance > distance_inside_boundary {
             return false;
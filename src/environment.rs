@@ -0,0 +1,217 @@
+use crate::color::{luminance, Color};
+use crate::image::RayonettaImage;
+use crate::sampler::Sampler;
+use crate::utils::{degrees_to_radians, PI};
+use crate::vec3::{unit_vector, Vec3};
+
+// Light arriving from infinitely far away, read from an equirectangular map.
+// The top row of the map is straight up (+y). Directions are importance sampled
+// by the luminance of the map so bright regions like the sun are found quickly
+pub struct EnvironmentLight {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    // Cumulative distributions over the rows, and over the pixels of every row
+    marginal_cdf: Vec<f64>,
+    conditional_cdf: Vec<f64>,
+    // Sum of all the sampling weights, zero for an all black map
+    total_weight: f64,
+
+    // Scale applied to the map radiance
+    pub intensity: f64,
+    // Rotation of the map around the y axis, in degrees
+    pub rotation: f64,
+}
+
+impl EnvironmentLight {
+    pub fn from_file(filename: &str) -> Result<Self, String> {
+        let image = RayonettaImage::from_file(filename)?;
+        Ok(EnvironmentLight::from_image(&image))
+    }
+
+    pub fn from_image(image: &RayonettaImage) -> Self {
+        EnvironmentLight::from_pixels(image.width() as usize, image.height() as usize, image.pixels())
+    }
+
+    // Map with a single color in every direction
+    pub fn from_color(color: Color) -> Self {
+        EnvironmentLight::from_pixels(1, 1, vec![color])
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), width * height, "Pixel count does not match the map size");
+
+        // Rows near the poles cover less solid angle, so they are weighted down by sin(theta)
+        let mut conditional_cdf = vec![0.0; height * (width + 1)];
+        let mut marginal_cdf = vec![0.0; height + 1];
+        for j in 0..height {
+            let sin_theta = f64::sin(PI * (j as f64 + 0.5) / height as f64);
+            let row = &mut conditional_cdf[j * (width + 1)..(j + 1) * (width + 1)];
+            for i in 0..width {
+                let weight = f64::max(luminance(&pixels[j * width + i]), 0.0) * sin_theta;
+                row[i + 1] = row[i] + weight;
+            }
+            marginal_cdf[j + 1] = marginal_cdf[j] + row[width];
+        }
+
+        EnvironmentLight {
+            width,
+            height,
+            pixels,
+            total_weight: marginal_cdf[height],
+            marginal_cdf,
+            conditional_cdf,
+            intensity: 1.0,
+            rotation: 0.0,
+        }
+    }
+
+    // Radiance arriving from the given direction
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        let (u, v) = self.direction_to_uv(direction);
+        let i = usize::min((u * self.width as f64) as usize, self.width - 1);
+        let j = usize::min((v * self.height as f64) as usize, self.height - 1);
+
+        self.intensity * self.pixels[j * self.width + i]
+    }
+
+    // Solid angle density of the directions returned by sample
+    pub fn pdf_value(&self, direction: Vec3) -> f64 {
+        if self.total_weight <= 0.0 {
+            return 1.0 / (4.0 * PI);
+        }
+
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = f64::sin(PI * v);
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let i = usize::min((u * self.width as f64) as usize, self.width - 1);
+        let j = usize::min((v * self.height as f64) as usize, self.height - 1);
+        let row = j * (self.width + 1);
+        let weight = self.conditional_cdf[row + i + 1] - self.conditional_cdf[row + i];
+
+        // Density over the map is constant inside a pixel, then mapped onto the sphere
+        let map_pdf = weight / self.total_weight * (self.width * self.height) as f64;
+        map_pdf / (2.0 * PI * PI * sin_theta)
+    }
+
+    pub fn sample(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+
+        // All black maps are sampled uniformly
+        if self.total_weight <= 0.0 {
+            let z = 1.0 - 2.0 * r1;
+            let r = f64::sqrt(f64::max(0.0, 1.0 - z * z));
+            let phi = 2.0 * PI * r2;
+            return Vec3::new(r * f64::cos(phi), r * f64::sin(phi), z);
+        }
+
+        let (j, dv) = sample_cdf(&self.marginal_cdf, r1);
+        let (i, du) = sample_cdf(&self.conditional_cdf[j * (self.width + 1)..(j + 1) * (self.width + 1)], r2);
+
+        let u = (i as f64 + du) / self.width as f64;
+        let v = (j as f64 + dv) / self.height as f64;
+        self.uv_to_direction(u, v)
+    }
+
+    // u goes around the y axis like the sphere texture coordinates, v from +y down to -y
+    fn direction_to_uv(&self, direction: Vec3) -> (f64, f64) {
+        let d = rotate_y(unit_vector(direction), -degrees_to_radians(self.rotation));
+
+        let phi = f64::atan2(-d.z(), d.x()) + PI;
+        let theta = f64::acos(d.y().clamp(-1.0, 1.0));
+
+        ((phi / (2.0 * PI)).clamp(0.0, 1.0), (theta / PI).clamp(0.0, 1.0))
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let phi = 2.0 * PI * u;
+        let theta = PI * v;
+
+        let d = Vec3::new(-f64::cos(phi) * f64::sin(theta), f64::cos(theta), f64::sin(phi) * f64::sin(theta));
+        rotate_y(d, degrees_to_radians(self.rotation))
+    }
+}
+
+fn rotate_y(v: Vec3, angle: f64) -> Vec3 {
+    let (sin_theta, cos_theta) = f64::sin_cos(angle);
+    Vec3::new(cos_theta * v.x() + sin_theta * v.z(), v.y(), -sin_theta * v.x() + cos_theta * v.z())
+}
+
+// Picks a bin of a cumulative distribution, returning it with the offset inside it
+fn sample_cdf(cdf: &[f64], r: f64) -> (usize, f64) {
+    let target = r * cdf[cdf.len() - 1];
+    // First bin whose upper edge is above the target, skipping empty bins
+    let index = cdf[1..].partition_point(|&c| c <= target).min(cdf.len() - 2);
+
+    let width = cdf[index + 1] - cdf[index];
+    let offset = if width > 0.0 { (target - cdf[index]) / width } else { 0.5 };
+
+    (index, offset.clamp(0.0, 1.0 - f64::EPSILON))
+}
+
+// Testing
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::IndependentSampler;
+    use crate::vec3::random_unit_sphere;
+
+    // Dim sky with a small bright patch
+    fn sky() -> EnvironmentLight {
+        let (width, height) = (32, 16);
+        let mut pixels = vec![Color::new(0.2, 0.3, 0.5); width * height];
+        pixels[4 * width + 20] = Color::new(500.0, 450.0, 400.0);
+        pixels[4 * width + 21] = Color::new(500.0, 450.0, 400.0);
+
+        let mut env = EnvironmentLight::from_pixels(width, height, pixels);
+        env.rotation = 30.0;
+        env
+    }
+
+    #[test]
+    fn test_direction_roundtrip() {
+        let env = sky();
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.7)] {
+            let (u2, v2) = env.direction_to_uv(env.uv_to_direction(u, v));
+            assert!((u - u2).abs() < 1e-9 && (v - v2).abs() < 1e-9);
+        }
+
+        // Top of the map is up
+        assert!(env.uv_to_direction(0.3, 0.0).y() > 0.999);
+    }
+
+    #[test]
+    fn test_pdf_integrates_to_one() {
+        let env = sky();
+        let mut sampler = IndependentSampler::new(5);
+        let n = 200000;
+        let mut sum = 0.0;
+        for s in 0..n {
+            sampler.start_pixel_sample(0, 0, s);
+            sum += env.pdf_value(random_unit_sphere(&mut sampler)) * 4.0 * PI;
+        }
+        assert!((sum / n as f64 - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_importance_sampling_matches_uniform() {
+        let env = sky();
+        let mut sampler = IndependentSampler::new(9);
+
+        // Total power seen from the sphere of directions, estimated both ways
+        let n = 200000;
+        let (mut importance, mut uniform) = (0.0, 0.0);
+        for s in 0..n {
+            sampler.start_pixel_sample(0, 0, s);
+            let d = env.sample(&mut sampler);
+            importance += luminance(&env.radiance(d)) / env.pdf_value(d);
+            uniform += luminance(&env.radiance(random_unit_sphere(&mut sampler))) * 4.0 * PI;
+        }
+
+        let (importance, uniform) = (importance / n as f64, uniform / n as f64);
+        assert!((importance - uniform).abs() / uniform < 0.05);
+    }
+}
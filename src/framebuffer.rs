@@ -0,0 +1,111 @@
+use std::io::{self, Write};
+
+use crate::color::{color_to_bytes, Color};
+
+// In-memory image holding linear RGB radiance, row-major from the top-left corner.
+// Also keeps how many samples were taken for every pixel
+#[derive(Clone, Debug)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    sample_counts: Vec<u32>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Color::empty(); width * height],
+            sample_counts: vec![0; width * height],
+        }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), width * height, "Pixel count does not match the framebuffer size");
+        Framebuffer { width, height, pixels, sample_counts: vec![0; width * height] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Color] {
+        &mut self.pixels
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Color]> {
+        self.pixels.chunks(self.width)
+    }
+
+    pub fn sample_count(&self, x: usize, y: usize) -> u32 {
+        self.sample_counts[y * self.width + x]
+    }
+
+    pub fn sample_counts(&self) -> &[u32] {
+        &self.sample_counts
+    }
+
+    pub fn sample_counts_mut(&mut self) -> &mut [u32] {
+        &mut self.sample_counts
+    }
+
+    // False color image of the sample counts, from blue (fewest) to red (most)
+    pub fn sample_heatmap(&self) -> Framebuffer {
+        let max = self.sample_counts.iter().copied().max().unwrap_or(0).max(1) as f64;
+        let pixels = self
+            .sample_counts
+            .iter()
+            .map(|&n| {
+                let t = n as f64 / max;
+                let r = (2.0 * t - 0.5).clamp(0.0, 1.0);
+                let g = 1.0 - (2.0 * t - 1.0).abs();
+                let b = (1.0 - 2.0 * t).clamp(0.0, 1.0);
+                // Squared so the gamma correction on output gives back the ramp
+                Color::new(r * r, g * g, b * b)
+            })
+            .collect();
+
+        Framebuffer::from_pixels(self.width, self.height, pixels)
+    }
+
+    // Linear RGB as 32-bit floats, three values per pixel
+    pub fn to_rgb_f32(&self) -> Vec<f32> {
+        self.pixels
+            .iter()
+            .flat_map(|c| [c.x() as f32, c.y() as f32, c.z() as f32])
+            .collect()
+    }
+
+    // Gamma-corrected 8-bit RGB, three bytes per pixel
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(color_to_bytes).collect()
+    }
+
+    // Writes the buffer as an ASCII (P3) PPM image
+    pub fn write_ppm<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "P3\n{0} {1}\n255", self.width, self.height)?;
+        for pixel in &self.pixels {
+            let [r, g, b] = color_to_bytes(pixel);
+            writeln!(out, "{r} {g} {b}")?;
+        }
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@ use crate::color::Color;
 use crate::interval::Interval;
 use crate::material::{Lambertian, Material};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{dot, Point3, Vec3};
 
 #[derive(Clone)]
@@ -43,4 +44,15 @@ impl HitRecord {
 pub trait Hittable: Sync + Send {
     fn hit(&self, r: &Ray, ray_t: &mut Interval, rec: &mut HitRecord) -> bool;
     fn bounding_box(&self) -> AABB;
+
+    // Density of directions from origin that hit this object at time, used to sample it as
+    // a light
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3, _time: f64) -> f64 {
+        0.0
+    }
+
+    // Random direction from origin towards where this object is at time
+    fn random(&self, _origin: Point3, _time: f64, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
 }
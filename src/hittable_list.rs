@@ -4,6 +4,8 @@ use crate::aabb::AABB;
 use crate::interval::Interval;
 use crate::ray::Ray;
 use crate::hittable::{Hittable, HitRecord};
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
 
 pub type HittableObject = Arc<dyn Hittable>;
 
@@ -54,4 +56,20 @@ impl Hittable for HittableList {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+
+    // Every object is picked with the same probability
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        if self.list.is_empty() {
+            return 0.0;
+        }
+
+        let weight = 1.0 / self.list.len() as f64;
+        self.list.iter().map(|object| weight * object.pdf_value(origin, direction, time)).sum()
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let size = self.list.len();
+        let index = usize::min((sampler.get_1d() * size as f64) as usize, size - 1);
+        self.list[index].random(origin, time, sampler)
+    }
 }
\ No newline at end of file
@@ -31,6 +31,15 @@ impl RayonettaImage {
         Color::new(rgb[0] as f64 / 255.0, rgb[1] as f64 / 255.0, rgb[2] as f64 / 255.0)
     }
 
+    // All pixels row by row. Floating point formats like HDR and EXR keep their unclamped values
+    pub fn pixels(&self) -> Vec<Color> {
+        self.image
+            .to_rgb32f()
+            .pixels()
+            .map(|p| Color::new(p.0[0] as f64, p.0[1] as f64, p.0[2] as f64))
+            .collect()
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
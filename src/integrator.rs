@@ -0,0 +1,286 @@
+use crate::color::Color;
+use crate::environment::EnvironmentLight;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::material::ScatterRecord;
+use crate::onb::Onb;
+use crate::pdf::{power_heuristic, Pdf};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::utils::INFINITY;
+use crate::vec3::{random_cosine_direction, Point3, Vec3};
+
+// Everything an integrator needs to know about the scene being rendered
+pub struct RenderContext<'a> {
+    pub world: &'a HittableList,
+    pub lights: &'a HittableList,
+    pub background: Color,
+    // Replaces the background when present
+    pub environment: Option<&'a EnvironmentLight>,
+    pub max_depth: i32,
+    pub russian_roulette_depth: i32,
+}
+
+impl RenderContext<'_> {
+    // Radiance of rays that leave the scene
+    pub fn miss(&self, r: &Ray) -> Color {
+        match self.environment {
+            Some(environment) => environment.radiance(r.direction()),
+            None => self.background,
+        }
+    }
+
+    // Whether there is anything to sample directly
+    pub fn has_lights(&self) -> bool {
+        !self.lights.list.is_empty() || self.environment.is_some()
+    }
+
+    // Density of light sampling picking direction from origin, with moving emitters placed
+    // as they are at time. The emitters and the environment are each picked half of the
+    // time when both are present
+    pub fn light_pdf(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        match (self.lights.list.is_empty(), self.environment) {
+            (true, None) => 0.0,
+            (false, None) => self.lights.pdf_value(origin, direction, time),
+            (true, Some(environment)) => environment.pdf_value(direction),
+            (false, Some(environment)) => {
+                0.5 * self.lights.pdf_value(origin, direction, time) + 0.5 * environment.pdf_value(direction)
+            }
+        }
+    }
+
+    pub fn sample_light(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        match (self.lights.list.is_empty(), self.environment) {
+            (_, None) => self.lights.random(origin, time, sampler),
+            (true, Some(environment)) => environment.sample(sampler),
+            (false, Some(environment)) => {
+                if sampler.get_1d() < 0.5 {
+                    self.lights.random(origin, time, sampler)
+                } else {
+                    environment.sample(sampler)
+                }
+            }
+        }
+    }
+}
+
+// Computes the radiance arriving along a camera ray
+pub trait Integrator: Sync + Send {
+    fn li(&self, r: &Ray, ctx: &RenderContext, sampler: &mut dyn Sampler) -> Color;
+}
+
+// Closest hit along the ray, if any
+fn intersect(r: &Ray, world: &HittableList) -> Option<HitRecord> {
+    let mut rec = HitRecord::new();
+    if world.hit(r, &mut Interval::new(0.001, INFINITY), &mut rec) {
+        Some(rec)
+    } else {
+        None
+    }
+}
+
+// Full global illumination: light sampling and material sampling combined with MIS
+// at every diffuse bounce, with Russian roulette ending long paths
+pub struct PathIntegrator;
+
+impl PathIntegrator {
+    // Follows the path one bounce at a time, accumulating the radiance it picks up
+    // weighted by the throughput of the bounces before it
+    fn trace(r: &Ray, ctx: &RenderContext, max_depth: i32, sampler: &mut dyn Sampler) -> Color {
+        let mut radiance = Color::empty();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *r;
+        // Density the material sampled the current ray with, None for camera and specular rays
+        let mut bsdf_pdf: Option<f64> = None;
+
+        for bounce in 0..max_depth {
+            let rec = match intersect(&ray, ctx.world) {
+                Some(rec) => rec,
+                None => {
+                    // A constant background is never light sampled, so it keeps its full weight
+                    let weight = if ctx.environment.is_some() { emission_weight(&ray, bsdf_pdf, ctx) } else { 1.0 };
+                    radiance = radiance + throughput * ctx.miss(&ray) * weight;
+                    break;
+                }
+            };
+
+            let color_from_emission = rec.mat.emitted(rec.u, rec.v, rec.p);
+            if !color_from_emission.near_zero() {
+                radiance = radiance + throughput * color_from_emission * emission_weight(&ray, bsdf_pdf, ctx);
+            }
+
+            let mut srec = ScatterRecord::new();
+            if !rec.mat.scatter(&ray, &rec, &mut srec, sampler) {
+                break;
+            }
+
+            match &srec.pdf {
+                // Specular lobes are followed as they are
+                None => {
+                    throughput = throughput * srec.attenuation;
+                    ray = srec.specular_ray;
+                    bsdf_pdf = None;
+                }
+                Some(pdf) => {
+                    // Only sample the lights when a bounce could still reach them
+                    if bounce + 1 < max_depth && ctx.has_lights() {
+                        radiance = radiance + throughput * sample_lights(&ray, &rec, &srec, pdf.as_ref(), ctx, sampler);
+                    }
+
+                    let scattered = Ray::new_with_time(rec.p, pdf.generate(sampler), ray.time());
+                    let pdf_value = pdf.value(scattered.direction());
+                    if pdf_value <= 0.0 {
+                        break;
+                    }
+
+                    let scattering_pdf = rec.mat.scattering_pdf(&ray, &rec, &scattered);
+                    throughput = throughput * srec.attenuation * scattering_pdf / pdf_value;
+                    ray = scattered;
+                    bsdf_pdf = Some(pdf_value);
+                }
+            }
+
+            // Russian roulette. Paths that can only carry little light are ended at random
+            // and the survivors are weighted up to keep the estimate unbiased
+            if bounce + 1 >= ctx.russian_roulette_depth {
+                let survival = throughput.max_component();
+                if survival < 1.0 {
+                    if sampler.get_1d() >= survival {
+                        break;
+                    }
+                    throughput = throughput / survival;
+                }
+            }
+        }
+
+        radiance
+    }
+}
+
+impl Integrator for PathIntegrator {
+    fn li(&self, r: &Ray, ctx: &RenderContext, sampler: &mut dyn Sampler) -> Color {
+        PathIntegrator::trace(r, ctx, ctx.max_depth, sampler)
+    }
+}
+
+// Lights hit by a sampled bounce share their contribution with light sampling
+fn emission_weight(r: &Ray, bsdf_pdf: Option<f64>, ctx: &RenderContext) -> f64 {
+    match bsdf_pdf {
+        Some(bsdf_pdf) if ctx.has_lights() => power_heuristic(bsdf_pdf, ctx.light_pdf(r.origin(), r.direction(), r.time())),
+        _ => 1.0,
+    }
+}
+
+// Next event estimation: one shadow ray towards a point on the lights or the environment,
+// weighted against the chance of the material sampling the same direction
+fn sample_lights(
+    r: &Ray,
+    rec: &HitRecord,
+    srec: &ScatterRecord,
+    bsdf: &dyn Pdf,
+    ctx: &RenderContext,
+    sampler: &mut dyn Sampler,
+) -> Color {
+    let direction = ctx.sample_light(rec.p, r.time(), sampler);
+    let light_pdf = ctx.light_pdf(rec.p, direction, r.time());
+    if light_pdf <= 0.0 {
+        return Color::empty();
+    }
+
+    let shadow_ray = Ray::new_with_time(rec.p, direction, r.time());
+    let emitted = match intersect(&shadow_ray, ctx.world) {
+        Some(light_rec) => light_rec.mat.emitted(light_rec.u, light_rec.v, light_rec.p),
+        None if ctx.environment.is_some() => ctx.miss(&shadow_ray),
+        None => return Color::empty(),
+    };
+
+    let scattering_pdf = rec.mat.scattering_pdf(r, rec, &shadow_ray);
+    let weight = power_heuristic(light_pdf, bsdf.value(direction));
+
+    (srec.attenuation * scattering_pdf * emitted) * weight / light_pdf
+}
+
+// Emitted light plus the light reaching the first surface straight from the emitters, seen
+// directly or through one specular or sampled bounce. Mirrors and glass show the emitters
+// behind them, but not the surfaces those emitters light
+pub struct DirectLightingIntegrator;
+
+impl Integrator for DirectLightingIntegrator {
+    fn li(&self, r: &Ray, ctx: &RenderContext, sampler: &mut dyn Sampler) -> Color {
+        PathIntegrator::trace(r, ctx, i32::min(ctx.max_depth, 2), sampler)
+    }
+}
+
+// Ambient occlusion: white where the cosine weighted hemisphere above the
+// first hit is open up to distance, darker where nearby geometry blocks it
+pub struct AmbientOcclusionIntegrator {
+    pub distance: f64,
+}
+
+impl AmbientOcclusionIntegrator {
+    pub fn new(distance: f64) -> Self {
+        AmbientOcclusionIntegrator { distance }
+    }
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn li(&self, r: &Ray, ctx: &RenderContext, sampler: &mut dyn Sampler) -> Color {
+        let rec = match intersect(r, ctx.world) {
+            Some(rec) => rec,
+            None => return Color::empty(),
+        };
+
+        let direction = Onb::new(rec.normal).transform(random_cosine_direction(sampler));
+        let probe = Ray::new_with_time(rec.p, direction, r.time());
+        let mut occluder = HitRecord::new();
+        if ctx.world.hit(&probe, &mut Interval::new(0.001, self.distance), &mut occluder) {
+            Color::empty()
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+}
+
+// Shading normal at the first hit, mapped from [-1, 1] to [0, 1]
+pub struct NormalIntegrator;
+
+impl Integrator for NormalIntegrator {
+    fn li(&self, r: &Ray, ctx: &RenderContext, _sampler: &mut dyn Sampler) -> Color {
+        match intersect(r, ctx.world) {
+            Some(rec) => 0.5 * (rec.normal + Vec3::new(1.0, 1.0, 1.0)),
+            None => Color::empty(),
+        }
+    }
+}
+
+// Texture coordinates at the first hit, u in red and v in green
+pub struct UvIntegrator;
+
+impl Integrator for UvIntegrator {
+    fn li(&self, r: &Ray, ctx: &RenderContext, _sampler: &mut dyn Sampler) -> Color {
+        match intersect(r, ctx.world) {
+            Some(rec) => Color::new(rec.u, rec.v, 0.0),
+            None => Color::empty(),
+        }
+    }
+}
+
+// Surface color at the first hit without any lighting. Emitters show their emission
+pub struct AlbedoIntegrator;
+
+impl Integrator for AlbedoIntegrator {
+    fn li(&self, r: &Ray, ctx: &RenderContext, sampler: &mut dyn Sampler) -> Color {
+        let rec = match intersect(r, ctx.world) {
+            Some(rec) => rec,
+            None => return ctx.miss(r),
+        };
+
+        let mut srec = ScatterRecord::new();
+        if rec.mat.scatter(r, &rec, &mut srec, sampler) {
+            srec.attenuation
+        } else {
+            rec.mat.emitted(rec.u, rec.v, rec.p)
+        }
+    }
+}
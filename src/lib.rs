@@ -1,19 +1,37 @@
 pub mod aabb;
 pub mod bvh;
 pub mod camera;
+pub mod camera_model;
 pub mod color;
 pub mod constant_medium;
+pub mod environment;
+pub mod framebuffer;
 pub mod hittable;
 pub mod hittable_list;
 pub mod image;
+pub mod integrator;
 pub mod interval;
 pub mod material;
+pub mod matrix;
+pub mod mesh;
+pub mod obj;
+pub mod onb;
+pub mod output;
+pub mod pdf;
 pub mod perlin;
+pub mod physical_camera;
 pub mod planar;
 pub mod plane;
+pub mod ply;
+pub mod quaternion;
 pub mod ray;
+pub mod sampler;
+pub mod scene;
+pub mod shutter;
 pub mod sphere;
+pub mod stl;
 pub mod texture;
 pub mod transformations;
+pub mod triangle;
 pub mod utils;
 pub mod vec3;
@@ -2,22 +2,51 @@ use std::sync::Arc;
 
 use crate::color::Color;
 use crate::hittable::HitRecord;
+use crate::pdf::{CosinePdf, Pdf, SpherePdf};
 use crate::ray::Ray;
 use crate::texture::{SolidColor, Texture};
-use crate::utils::random_uniform;
+use crate::sampler::Sampler;
+use crate::utils::PI;
 use crate::vec3::{dot, random_unit_sphere, reflect, refract, unit_vector, Vec3, Point3};
 
+// Outcome of a scatter event. Diffuse lobes carry the pdf their direction is drawn from,
+// specular lobes carry a single outgoing ray and are followed without pdf weighting
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub pdf: Option<Box<dyn Pdf>>,
+    pub specular_ray: Ray,
+}
+
+impl ScatterRecord {
+    pub fn new() -> Self {
+        ScatterRecord {
+            attenuation: Color::empty(),
+            pdf: None,
+            specular_ray: Ray::new(Point3::empty(), Vec3::empty()),
+        }
+    }
+
+    pub fn is_specular(&self) -> bool {
+        self.pdf.is_none()
+    }
+}
+
+impl Default for ScatterRecord {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub trait Material: Sync + Send {
-    fn scatter(
-        &self,
-        _r_in: &Ray,
-        _rec: &HitRecord,
-        _attenuation: &mut Color,
-        _scattered: &mut Ray,
-    ) -> bool {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _srec: &mut ScatterRecord, _sampler: &mut dyn Sampler) -> bool {
         false
     }
 
+    // Density the material itself scatters into the given direction with
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
     fn emitted(&self, _u: f64, _v: f64, _p: Point3) -> Color {
         Color::empty()
     }
@@ -39,22 +68,16 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        let mut scatter_direction = rec.normal + random_unit_sphere();
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
-        }
-
-        *scattered = Ray::new_with_time(rec.p, scatter_direction, r_in.time());
-        *attenuation = self.texture.value(rec.u, rec.v, rec.p);
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord, _sampler: &mut dyn Sampler) -> bool {
+        srec.attenuation = self.texture.value(rec.u, rec.v, rec.p);
+        srec.pdf = Some(Box::new(CosinePdf::new(rec.normal)));
         true
     }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = dot(rec.normal, unit_vector(scattered.direction()));
+        if cos_theta < 0.0 { 0.0 } else { cos_theta / PI }
+    }
 }
 
 // Metallic material
@@ -70,19 +93,14 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(
-            &self,
-            r_in: &Ray,
-            rec: &HitRecord,
-            attenuation: &mut Color,
-            scattered: &mut Ray,
-        ) -> bool {
-        
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord, sampler: &mut dyn Sampler) -> bool {
         let mut reflected = reflect(r_in.direction(), rec.normal);
-        reflected = unit_vector(reflected) + (self.fuzz * random_unit_sphere());
-        *scattered = Ray::new_with_time(rec.p, reflected, r_in.time());
-        *attenuation = self.albedo;
-        dot(scattered.direction(), rec.normal) > 0.0
+        reflected = unit_vector(reflected) + (self.fuzz * random_unit_sphere(sampler));
+
+        srec.attenuation = self.albedo;
+        srec.pdf = None;
+        srec.specular_ray = Ray::new_with_time(rec.p, reflected, r_in.time());
+        dot(reflected, rec.normal) > 0.0
     }
 }
 
@@ -105,14 +123,9 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(
-            &self,
-            r_in: &Ray,
-            rec: &HitRecord,
-            attenuation: &mut Color,
-            scattered: &mut Ray,
-        ) -> bool {
-        *attenuation = Color::new(1.0, 1.0, 1.0);
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord, sampler: &mut dyn Sampler) -> bool {
+        srec.attenuation = Color::new(1.0, 1.0, 1.0);
+        srec.pdf = None;
         let ri = if rec.front_face { 1.0 / self.refraction_index } else { self.refraction_index };
 
         let unit_direction = unit_vector(r_in.direction());
@@ -122,13 +135,13 @@ impl Material for Dielectric {
         let cannot_refract = ri * sin_theta > 1.0;
 
         let direction: Vec3;
-        if cannot_refract || Dielectric::reflectance(cos_theta, ri) > random_uniform() {
+        if cannot_refract || Dielectric::reflectance(cos_theta, ri) > sampler.get_1d() {
             direction = reflect(unit_direction, rec.normal);
         } else {
             direction = refract(unit_direction, rec.normal, ri);
         }
 
-        *scattered = Ray::new_with_time(rec.p, direction, r_in.time());
+        srec.specular_ray = Ray::new_with_time(rec.p, direction, r_in.time());
         true
     }
 }
@@ -169,15 +182,13 @@ impl Isotropic {
 }
 
 impl Material for Isotropic {
-    fn scatter(
-            &self,
-            r_in: &Ray,
-            rec: &HitRecord,
-            attenuation: &mut Color,
-            scattered: &mut Ray,
-        ) -> bool {
-        *scattered = Ray::new_with_time(rec.p, random_unit_sphere(), r_in.time());
-        *attenuation = self.texture.value(rec.u, rec.v, rec.p);
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord, _sampler: &mut dyn Sampler) -> bool {
+        srec.attenuation = self.texture.value(rec.u, rec.v, rec.p);
+        srec.pdf = Some(Box::new(SpherePdf));
         true
     }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        1.0 / (4.0 * PI)
+    }
 }
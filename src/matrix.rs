@@ -0,0 +1,190 @@
+use std::ops;
+
+use crate::aabb::AABB;
+use crate::utils::{degrees_to_radians, INFINITY};
+use crate::vec3::{cross, dot, unit_vector, Point3, Vec3};
+
+// Row-major 4x4 matrix of an affine transform. Points are column vectors, so in a * b
+// the transform b is applied first. The bottom row is kept at 0 0 0 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Matrix4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Matrix4 {
+    pub const IDENTITY: Matrix4 = Matrix4 {
+        m: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
+    };
+
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Matrix4 { m }
+    }
+
+    // Matrix with the given linear part in its first three columns and the translation in the last
+    fn from_columns(x: Vec3, y: Vec3, z: Vec3, offset: Vec3) -> Self {
+        Matrix4::new([
+            [x.x(), y.x(), z.x(), offset.x()],
+            [x.y(), y.y(), z.y(), offset.y()],
+            [x.z(), y.z(), z.z(), offset.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        Matrix4::from_columns(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0), offset)
+    }
+
+    pub fn scale(factors: Vec3) -> Self {
+        Matrix4::from_columns(
+            Vec3::new(factors.x(), 0.0, 0.0),
+            Vec3::new(0.0, factors.y(), 0.0),
+            Vec3::new(0.0, 0.0, factors.z()),
+            Vec3::empty(),
+        )
+    }
+
+    // Counterclockwise rotation in degrees, looking down the axis towards the origin.
+    // A rotation about (0, 1, 0) turns objects the same way as RotateY
+    pub fn rotate(axis: Vec3, angle: f64) -> Self {
+        let k = unit_vector(axis);
+        let radians = degrees_to_radians(angle);
+        let (sin, cos) = radians.sin_cos();
+
+        // Rodrigues' formula, one column per rotated basis vector
+        let column = |e: Vec3| cos * e + sin * cross(k, e) + ((1.0 - cos) * dot(k, e)) * k;
+        Matrix4::from_columns(
+            column(Vec3::new(1.0, 0.0, 0.0)),
+            column(Vec3::new(0.0, 1.0, 0.0)),
+            column(Vec3::new(0.0, 0.0, 1.0)),
+            Vec3::empty(),
+        )
+    }
+
+    // Places an object at from, with its +z axis facing at and its +y axis towards up
+    pub fn look_at(from: Point3, at: Point3, up: Vec3) -> Self {
+        let z = unit_vector(at - from);
+        let x = unit_vector(cross(up, z));
+        let y = cross(z, x);
+        Matrix4::from_columns(x, y, z, from)
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Matrix4::new(m)
+    }
+
+    // Gauss-Jordan elimination with partial pivoting. None for singular matrices,
+    // like a scale by zero
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Matrix4::IDENTITY.m;
+
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+
+            for row in 0..4 {
+                let factor = a[row][col];
+                if row == col || factor == 0.0 {
+                    continue;
+                }
+                for j in 0..4 {
+                    a[row][j] -= factor * a[col][j];
+                    inv[row][j] -= factor * inv[col][j];
+                }
+            }
+        }
+
+        Some(Matrix4::new(inv))
+    }
+
+    // Determinant of the linear part, the factor the transform scales volumes by
+    pub fn linear_determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        self.transform_vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    // Directions and offsets, which the translation does not move
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+
+    // Normals transform with the inverse transpose to stay perpendicular to the surface,
+    // so this multiplies by the transpose and is meant to be called on the inverse. Not normalized
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * n.x() + m[1][0] * n.y() + m[2][0] * n.z(),
+            m[0][1] * n.x() + m[1][1] * n.y() + m[2][1] * n.z(),
+            m[0][2] * n.x() + m[1][2] * n.y() + m[2][2] * n.z(),
+        )
+    }
+
+    // Box around the eight transformed corners. Unbounded boxes stay unbounded
+    pub fn transform_box(&self, bbox: &AABB) -> AABB {
+        if !bbox.is_bounded() {
+            return AABB::UNIVERSE;
+        }
+
+        let mut min = Point3::new(INFINITY, INFINITY, INFINITY);
+        let mut max = Point3::new(-INFINITY, -INFINITY, -INFINITY);
+        for corner in 0..8 {
+            let p = Point3::new(
+                if corner & 1 == 0 { bbox.x.min } else { bbox.x.max },
+                if corner & 2 == 0 { bbox.y.min } else { bbox.y.max },
+                if corner & 4 == 0 { bbox.z.min } else { bbox.z.max },
+            );
+            let q = self.transform_point(p);
+            for c in 0..3 {
+                min[c] = min[c].min(q[c]);
+                max[c] = max[c].max(q[c]);
+            }
+        }
+
+        AABB::from_points(min, max)
+    }
+}
+
+impl Default for Matrix4 {
+    fn default() -> Self {
+        Matrix4::IDENTITY
+    }
+}
+
+impl ops::Mul for Matrix4 {
+    type Output = Self;
+    fn mul(self, rhs: Matrix4) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Matrix4::new(m)
+    }
+}
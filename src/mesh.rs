@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::bvh::BVH;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::color::Color;
+use crate::material::{Lambertian, Material};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::VertexColorTexture;
+use crate::triangle::{intersect_triangle, triangle_bbox};
+use crate::utils::INFINITY;
+use crate::vec3::{cross, dot, unit_vector, Point3, Vec3};
+
+// Indexed triangle geometry. Normals, uvs and colors are either empty or hold one entry per vertex
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<[f64; 2]>,
+    pub colors: Vec<Color>,
+    pub indices: Vec<[usize; 3]>,
+}
+
+impl MeshData {
+    pub fn new(positions: Vec<Point3>, indices: Vec<[usize; 3]>) -> Self {
+        MeshData { positions, indices, ..Default::default() }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.indices.is_empty() {
+            return Err("Mesh has no triangles.".to_string());
+        }
+        if !self.normals.is_empty() && self.normals.len() != self.positions.len() {
+            return Err(format!("Mesh has {} normals for {} vertices.", self.normals.len(), self.positions.len()));
+        }
+        if !self.uvs.is_empty() && self.uvs.len() != self.positions.len() {
+            return Err(format!("Mesh has {} uvs for {} vertices.", self.uvs.len(), self.positions.len()));
+        }
+        if !self.colors.is_empty() && self.colors.len() != self.positions.len() {
+            return Err(format!("Mesh has {} colors for {} vertices.", self.colors.len(), self.positions.len()));
+        }
+        if let Some(face) = self.indices.iter().position(|f| f.iter().any(|&i| i >= self.positions.len())) {
+            return Err(format!("Triangle {face} uses a vertex out of range."));
+        }
+        Ok(())
+    }
+}
+
+struct SharedMesh {
+    data: MeshData,
+    // Total surface area, which light sampling spreads its points over
+    area: f64,
+    mat: Arc<dyn Material + Sync + Send>,
+    // Faces pass their index along in u, for a VertexColorTexture
+    face_in_u: bool,
+}
+
+impl SharedMesh {
+    fn vertices(&self, face: usize) -> [Point3; 3] {
+        let [a, b, c] = self.data.indices[face];
+        [self.data.positions[a], self.data.positions[b], self.data.positions[c]]
+    }
+}
+
+// One face of a mesh, pointing back to the shared vertex data
+struct MeshTriangle {
+    mesh: Arc<SharedMesh>,
+    face: usize,
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> [Point3; 3] {
+        self.mesh.vertices(self.face)
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, ray_t: &mut Interval, rec: &mut HitRecord) -> bool {
+        let [p0, p1, p2] = self.vertices();
+        let (t, b) = match intersect_triangle(r, ray_t, p0, p1, p2) {
+            Some(hit) => hit,
+            None => return false,
+        };
+
+        let data = &self.mesh.data;
+        let index = data.indices[self.face];
+
+        rec.t = t;
+        rec.p = b[0] * p0 + b[1] * p1 + b[2] * p2;
+        rec.set_face_normal(r, &unit_vector(cross(p1 - p0, p2 - p0)));
+
+        // Smooth shading, kept on the same side as the geometric normal
+        if !data.normals.is_empty() {
+            let n = b[0] * data.normals[index[0]] + b[1] * data.normals[index[1]] + b[2] * data.normals[index[2]];
+            if !n.near_zero() {
+                let shading_normal = unit_vector(n);
+                rec.normal = if dot(shading_normal, rec.normal) < 0.0 { -shading_normal } else { shading_normal };
+            }
+        }
+
+        if self.mesh.face_in_u {
+            // Halved so the fraction never rounds up into the next face
+            rec.u = self.face as f64 + 0.5 * b[1];
+            rec.v = b[2];
+        } else if data.uvs.is_empty() {
+            rec.u = b[1];
+            rec.v = b[2];
+        } else {
+            let [uv0, uv1, uv2] = [data.uvs[index[0]], data.uvs[index[1]], data.uvs[index[2]]];
+            rec.u = b[0] * uv0[0] + b[1] * uv1[0] + b[2] * uv2[0];
+            rec.v = b[0] * uv0[1] + b[1] * uv1[1] + b[2] * uv2[1];
+        }
+        rec.mat = self.mesh.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        let [p0, p1, p2] = self.vertices();
+        triangle_bbox(p0, p1, p2)
+    }
+
+    // Density of sampling points uniformly over the whole mesh, from the part on this face
+    fn pdf_value(&self, origin: Point3, direction: Vec3, _time: f64) -> f64 {
+        let [p0, p1, p2] = self.vertices();
+        let r = Ray::new(origin, direction);
+        match intersect_triangle(&r, &Interval::new(0.001, INFINITY), p0, p1, p2) {
+            Some((t, _)) => {
+                let normal = unit_vector(cross(p1 - p0, p2 - p0));
+                let distance_squared = t * t * direction.length_squared();
+                let cosine = f64::abs(dot(direction, normal) / direction.length());
+                distance_squared / (cosine * self.mesh.area)
+            }
+            None => 0.0,
+        }
+    }
+}
+
+// Triangle mesh with its own BVH over the faces
+pub struct TriangleMesh {
+    bvh: BVH,
+    mesh: Arc<SharedMesh>,
+    // Running total of the face areas, to sample points uniformly over the surface
+    area_cdf: Vec<f64>,
+}
+
+impl TriangleMesh {
+    pub fn new(data: MeshData, mat: Arc<dyn Material + Sync + Send>) -> Result<Self, String> {
+        data.validate()?;
+        Ok(TriangleMesh::build(data, mat, false))
+    }
+
+    // Diffuse mesh colored by blending its vertex colors across every face
+    pub fn with_vertex_colors(data: MeshData) -> Result<Self, String> {
+        if data.colors.is_empty() {
+            return Err("Mesh has no vertex colors.".to_string());
+        }
+        data.validate()?;
+
+        let colors = data.indices.iter().map(|&[a, b, c]| [data.colors[a], data.colors[b], data.colors[c]]).collect();
+        let texture = Arc::new(VertexColorTexture::new(colors));
+        Ok(TriangleMesh::build(data, Arc::new(Lambertian::from_texture(texture)), true))
+    }
+
+    // Data already validated
+    fn build(data: MeshData, mat: Arc<dyn Material + Sync + Send>, face_in_u: bool) -> Self {
+        let triangle_count = data.triangle_count();
+        let mut area_cdf = Vec::with_capacity(triangle_count + 1);
+        area_cdf.push(0.0);
+        for (face, &[a, b, c]) in data.indices.iter().enumerate() {
+            let [p0, p1, p2] = [data.positions[a], data.positions[b], data.positions[c]];
+            area_cdf.push(area_cdf[face] + 0.5 * cross(p1 - p0, p2 - p0).length());
+        }
+
+        let mesh = Arc::new(SharedMesh { data, area: area_cdf[triangle_count], mat, face_in_u });
+        let mut faces: Vec<Arc<dyn Hittable>> = (0..triangle_count)
+            .map(|face| Arc::new(MeshTriangle { mesh: mesh.clone(), face }) as Arc<dyn Hittable>)
+            .collect();
+
+        TriangleMesh { bvh: BVH::new(&mut faces), mesh, area_cdf }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.mesh.data.triangle_count()
+    }
+
+    pub fn area(&self) -> f64 {
+        self.area_cdf[self.area_cdf.len() - 1]
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, ray_t: &mut Interval, rec: &mut HitRecord) -> bool {
+        self.bvh.hit(r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bvh.bounding_box()
+    }
+
+    // Points are spread uniformly over the whole surface, so every face the direction
+    // passes through adds to the density. The BVH finds them without testing every face
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        if self.area() <= 0.0 {
+            return 0.0;
+        }
+
+        let mut pdf = 0.0;
+        let r = Ray::new(origin, direction);
+        self.bvh.for_each_along(&r, Interval::new(0.001, INFINITY), |face| pdf += face.pdf_value(origin, direction, time));
+        pdf
+    }
+
+    fn random(&self, origin: Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let target = sampler.get_1d() * self.area();
+        let face = self.area_cdf[1..].partition_point(|&a| a <= target).min(self.triangle_count() - 1);
+
+        let [p0, p1, p2] = self.mesh.vertices(face);
+        let (r1, r2) = sampler.get_2d();
+        let s = f64::sqrt(r1);
+        let p = (1.0 - s) * p0 + (s * (1.0 - r2)) * p1 + (s * r2) * p2;
+        p - origin
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use log::warn;
+
+use crate::color::Color;
+use crate::hittable_list::HittableList;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::mesh::{MeshData, TriangleMesh};
+use crate::texture::ImageTexture;
+use crate::vec3::{Point3, Vec3};
+
+// Wavefront OBJ and MTL import.
+//
+// Faces are grouped by material and every group becomes one TriangleMesh. Polygons are
+// triangulated as fans. MTL materials map onto the renderer materials as follows:
+//   Ke not black                    -> DiffuseLight(Ke), also added to the lights
+//   d < 1, Tr > 0 or illum 4, 6, 7  -> Dielectric(Ni)
+//   illum 3 or Ks brighter than Kd  -> Metal(Ks), fuzz from the Ns exponent
+//   otherwise                       -> Lambertian(Kd), or the map_Kd texture when present
+
+#[derive(Debug)]
+pub enum ObjError {
+    // A file could not be read
+    Io { path: PathBuf, message: String },
+    // Malformed statement, with the 1-based line it is on
+    Parse { path: PathBuf, line: usize, message: String },
+    // A texture referenced by a material could not be loaded
+    Texture { path: PathBuf, message: String },
+    // The faces do not form a valid mesh
+    Mesh { message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io { path, message } => write!(f, "{}: {message}", path.display()),
+            ObjError::Parse { path, line, message } => write!(f, "{}:{line}: {message}", path.display()),
+            ObjError::Texture { path, message } => write!(f, "{}: cannot load texture: {message}", path.display()),
+            ObjError::Mesh { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+// Imported geometry. Emissive meshes are in both lists so they can be registered as camera lights
+pub struct ObjScene {
+    pub objects: HittableList,
+    pub lights: HittableList,
+    pub triangle_count: usize,
+}
+
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<ObjScene, ObjError> {
+    let path = path.as_ref();
+    let source = read_file(path)?;
+    parse_obj(&source, path)
+}
+
+fn read_file(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|e| ObjError::Io { path: path.to_path_buf(), message: e.to_string() })
+}
+
+// Position, uv and normal indices of one face corner, all 0-based
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+// Line by line parsing context, for error reporting
+struct Line<'a> {
+    path: &'a Path,
+    number: usize,
+}
+
+impl Line<'_> {
+    fn error(&self, message: String) -> ObjError {
+        ObjError::Parse { path: self.path.to_path_buf(), line: self.number, message }
+    }
+
+    fn numbers<'b>(&self, args: impl Iterator<Item = &'b str>) -> Result<Vec<f64>, ObjError> {
+        args.map(|a| a.parse::<f64>().map_err(|_| self.error(format!("Invalid number '{a}'")))).collect()
+    }
+
+    fn vector<'b>(&self, keyword: &str, args: impl Iterator<Item = &'b str>) -> Result<Vec3, ObjError> {
+        let values = self.numbers(args)?;
+        if values.len() < 3 {
+            return Err(self.error(format!("'{keyword}' needs 3 values, found {}", values.len())));
+        }
+        Ok(Vec3::new(values[0], values[1], values[2]))
+    }
+
+    fn scalar<'b>(&self, keyword: &str, args: impl Iterator<Item = &'b str>) -> Result<f64, ObjError> {
+        match self.numbers(args)?.first() {
+            Some(&value) => Ok(value),
+            None => Err(self.error(format!("'{keyword}' needs a value"))),
+        }
+    }
+
+    // OBJ indices start at 1, negative ones count back from the last element
+    fn index(&self, text: &str, count: usize, what: &str) -> Result<usize, ObjError> {
+        let value = text.parse::<i64>().map_err(|_| self.error(format!("Invalid {what} index '{text}'")))?;
+        let resolved = if value > 0 { value - 1 } else { count as i64 + value };
+        if value == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(self.error(format!("{what} index {value} out of range, {count} defined so far")));
+        }
+        Ok(resolved as usize)
+    }
+}
+
+// Parses OBJ source. path names the file in errors and locates the mtllib files
+pub fn parse_obj(source: &str, path: &Path) -> Result<ObjScene, ObjError> {
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut positions: Vec<Point3> = Vec::new();
+    let mut uvs: Vec<[f64; 2]> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut materials: HashMap<String, MtlMaterial> = HashMap::new();
+
+    // Faces of every material, in the order the materials are first used
+    let mut groups: Vec<(String, Vec<[Corner; 3]>)> = Vec::new();
+    let mut current = String::new();
+
+    for (number, text) in source.lines().enumerate() {
+        let line = Line { path, number: number + 1 };
+        let text = text.split('#').next().unwrap_or("").trim();
+        let mut args = text.split_whitespace();
+        let keyword = match args.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        match keyword {
+            "v" => positions.push(line.vector(keyword, args)?),
+            "vn" => normals.push(line.vector(keyword, args)?),
+            "vt" => {
+                let values = line.numbers(args)?;
+                match values.len() {
+                    0 => return Err(line.error("'vt' needs at least 1 value".to_string())),
+                    1 => uvs.push([values[0], 0.0]),
+                    _ => uvs.push([values[0], values[1]]),
+                }
+            }
+            "f" => {
+                let mut corners = Vec::new();
+                for vertex in args {
+                    let mut parts = vertex.split('/');
+                    let position = line.index(parts.next().unwrap_or(""), positions.len(), "Vertex")?;
+                    let uv = match parts.next() {
+                        Some(uv) if !uv.is_empty() => Some(line.index(uv, uvs.len(), "Texture coordinate")?),
+                        _ => None,
+                    };
+                    let normal = match parts.next() {
+                        Some(normal) if !normal.is_empty() => Some(line.index(normal, normals.len(), "Normal")?),
+                        _ => None,
+                    };
+                    corners.push(Corner { position, uv, normal });
+                }
+
+                if corners.len() < 3 {
+                    return Err(line.error(format!("Face needs at least 3 vertices, found {}", corners.len())));
+                }
+
+                let group = match groups.iter().position(|(name, _)| *name == current) {
+                    Some(group) => group,
+                    None => {
+                        groups.push((current.clone(), Vec::new()));
+                        groups.len() - 1
+                    }
+                };
+                for i in 1..corners.len() - 1 {
+                    groups[group].1.push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            "usemtl" => current = rest_of_line(text, keyword).to_string(),
+            "mtllib" => {
+                let mtl_path = base_dir.join(rest_of_line(text, keyword));
+                let mtl_source = read_file(&mtl_path)?;
+                materials.extend(parse_mtl(&mtl_source, &mtl_path)?);
+            }
+            // Objects, groups, smoothing groups and anything else are not needed
+            _ => {}
+        }
+    }
+
+    let mut scene = ObjScene { objects: HittableList::new(), lights: HittableList::new(), triangle_count: 0 };
+    for (name, faces) in groups {
+        let (mat, emissive) = match materials.get(&name) {
+            Some(material) => (material.build()?, material.is_emissive()),
+            None => {
+                if !name.is_empty() {
+                    warn!("Material '{name}' is not defined, using a gray diffuse one");
+                }
+                (Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))) as Arc<dyn Material>, false)
+            }
+        };
+
+        let data = build_mesh_data(&faces, &positions, &uvs, &normals);
+        scene.triangle_count += data.triangle_count();
+
+        let mesh = Arc::new(TriangleMesh::new(data, mat).map_err(|message| ObjError::Mesh { message })?);
+        scene.objects.add(mesh.clone());
+        if emissive {
+            scene.lights.add(mesh);
+        }
+    }
+
+    Ok(scene)
+}
+
+// Arguments of a statement taken as one string, so file names may contain spaces
+fn rest_of_line<'a>(text: &'a str, keyword: &str) -> &'a str {
+    text[keyword.len()..].trim()
+}
+
+// The file name of a texture map, after the options in front of it. The name is the rest of
+// the line, so it may hold spaces. -o, -s and -t take one to three numbers, the others a
+// fixed number of arguments
+fn map_file(mut rest: &str) -> Result<&str, String> {
+    while rest.starts_with('-') {
+        let (option, tail) = split_word(rest);
+        let (min, max) = match option {
+            "-o" | "-s" | "-t" => (1, 3),
+            "-mm" => (2, 2),
+            "-blendu" | "-blendv" | "-boost" | "-texres" | "-clamp" | "-bm" | "-imfchan" | "-type" | "-cc" => (1, 1),
+            _ => return Err(format!("Unknown map option '{option}'")),
+        };
+        rest = tail;
+        for count in 0..max {
+            let (argument, tail) = split_word(rest);
+            if count >= min && argument.parse::<f64>().is_err() {
+                break;
+            }
+            if argument.is_empty() {
+                return Err(format!("Map option '{option}' is missing arguments"));
+            }
+            rest = tail;
+        }
+    }
+    Ok(rest)
+}
+
+// First word of text and what follows it
+fn split_word(text: &str) -> (&str, &str) {
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim_start()),
+        None => (text, ""),
+    }
+}
+
+// Gives every distinct corner its own vertex. Normals and uvs are only kept
+// when every corner of the group has them
+fn build_mesh_data(faces: &[[Corner; 3]], positions: &[Point3], uvs: &[[f64; 2]], normals: &[Vec3]) -> MeshData {
+    let has_uvs = faces.iter().flatten().all(|c| c.uv.is_some());
+    let has_normals = faces.iter().flatten().all(|c| c.normal.is_some());
+
+    let mut data = MeshData::default();
+    let mut vertices: HashMap<Corner, usize> = HashMap::new();
+    for face in faces {
+        let mut triangle = [0; 3];
+        for (k, corner) in face.iter().enumerate() {
+            triangle[k] = *vertices.entry(*corner).or_insert_with(|| {
+                data.positions.push(positions[corner.position]);
+                if has_uvs {
+                    data.uvs.push(uvs[corner.uv.unwrap_or(0)]);
+                }
+                if has_normals {
+                    data.normals.push(normals[corner.normal.unwrap_or(0)]);
+                }
+                data.positions.len() - 1
+            });
+        }
+        data.indices.push(triangle);
+    }
+
+    data
+}
+
+// Material statements we understand from an MTL file
+#[derive(Clone, Debug)]
+struct MtlMaterial {
+    kd: Color,
+    ks: Color,
+    ke: Color,
+    ns: f64,
+    ni: f64,
+    dissolve: f64,
+    illum: i32,
+    map_kd: Option<PathBuf>,
+}
+
+impl MtlMaterial {
+    fn new() -> Self {
+        MtlMaterial {
+            kd: Color::new(0.8, 0.8, 0.8),
+            ks: Color::empty(),
+            ke: Color::empty(),
+            ns: 0.0,
+            ni: 1.0,
+            dissolve: 1.0,
+            illum: 2,
+            map_kd: None,
+        }
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.ke.max_component() > 0.0
+    }
+
+    fn build(&self) -> Result<Arc<dyn Material>, ObjError> {
+        if self.is_emissive() {
+            return Ok(Arc::new(DiffuseLight::from_color(self.ke)));
+        }
+
+        if self.dissolve < 1.0 || matches!(self.illum, 4 | 6 | 7) {
+            // Ni defaults to 1, which would make the glass invisible
+            let refraction_index = if self.ni > 1.0 { self.ni } else { 1.5 };
+            return Ok(Arc::new(Dielectric::new(refraction_index)));
+        }
+
+        if self.illum == 3 || self.ks.max_component() > self.kd.max_component() {
+            // Rough mapping from the Phong exponent to the fuzz radius
+            let fuzz = f64::sqrt(2.0 / (self.ns.max(0.0) + 2.0));
+            return Ok(Arc::new(Metal::new(self.ks, fuzz)));
+        }
+
+        match &self.map_kd {
+            Some(path) => {
+                let texture = ImageTexture::from_file(&path.to_string_lossy())
+                    .map_err(|message| ObjError::Texture { path: path.clone(), message })?;
+                Ok(Arc::new(Lambertian::from_texture(Arc::new(texture))))
+            }
+            None => Ok(Arc::new(Lambertian::new(self.kd))),
+        }
+    }
+}
+
+// Parses MTL source. path names the file in errors and locates the texture maps
+fn parse_mtl(source: &str, path: &Path) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (number, text) in source.lines().enumerate() {
+        let line = Line { path, number: number + 1 };
+        let text = text.split('#').next().unwrap_or("").trim();
+        let mut args = text.split_whitespace();
+        let keyword = match args.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((rest_of_line(text, keyword).to_string(), MtlMaterial::new()));
+            continue;
+        }
+
+        let material = match current.as_mut() {
+            Some((_, material)) => material,
+            None => return Err(line.error(format!("'{keyword}' before any 'newmtl'"))),
+        };
+
+        match keyword {
+            "Kd" => material.kd = line.vector(keyword, args)?,
+            "Ks" => material.ks = line.vector(keyword, args)?,
+            "Ke" => material.ke = line.vector(keyword, args)?,
+            "Ns" => material.ns = line.scalar(keyword, args)?,
+            "Ni" => material.ni = line.scalar(keyword, args)?,
+            "d" => material.dissolve = line.scalar(keyword, args)?,
+            "Tr" => material.dissolve = 1.0 - line.scalar(keyword, args)?,
+            "illum" => material.illum = line.scalar(keyword, args)? as i32,
+            "map_Kd" => {
+                let file = map_file(rest_of_line(text, keyword)).map_err(|e| line.error(e))?;
+                if file.is_empty() {
+                    return Err(line.error("'map_Kd' needs a file name".to_string()));
+                }
+                material.map_kd = Some(base_dir.join(file));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+
+    Ok(materials)
+}
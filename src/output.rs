@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::{DynamicImage, ImageFormat, Rgb32FImage, RgbImage};
+
+use crate::framebuffer::Framebuffer;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    // 8-bit, gamma encoded
+    Png,
+    Jpeg,
+    Ppm,
+    // Linear floating point, unclamped
+    Hdr,
+    Exr,
+}
+
+impl OutputFormat {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .ok_or_else(|| "Output file has no extension.".to_string())?;
+
+        match extension.as_str() {
+            "png" => Ok(OutputFormat::Png),
+            "jpg" | "jpeg" => Ok(OutputFormat::Jpeg),
+            "ppm" => Ok(OutputFormat::Ppm),
+            "hdr" => Ok(OutputFormat::Hdr),
+            "exr" => Ok(OutputFormat::Exr),
+            other => Err(format!("Unsupported output format '{other}'.")),
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, OutputFormat::Hdr | OutputFormat::Exr)
+    }
+}
+
+// Saves the framebuffer, choosing the encoder from the file extension
+pub fn save_image<P: AsRef<Path>>(image: &Framebuffer, path: P) -> Result<(), String> {
+    let format = OutputFormat::from_path(&path)?;
+    save_image_with_format(image, path, format)
+}
+
+pub fn save_image_with_format<P: AsRef<Path>>(
+    image: &Framebuffer,
+    path: P,
+    format: OutputFormat,
+) -> Result<(), String> {
+    let path = path.as_ref();
+
+    if format == OutputFormat::Ppm {
+        let file = File::create(path).map_err(|e| format!("Could not create {}: {e}", path.display()))?;
+        return image
+            .write_ppm(&mut BufWriter::new(file))
+            .map_err(|e| format!("Could not write {}: {e}", path.display()));
+    }
+
+    let dynamic = if format.is_hdr() {
+        DynamicImage::ImageRgb32F(to_rgb32f_image(image))
+    } else {
+        DynamicImage::ImageRgb8(to_rgb8_image(image))
+    };
+
+    let image_format = match format {
+        OutputFormat::Png => ImageFormat::Png,
+        OutputFormat::Jpeg => ImageFormat::Jpeg,
+        OutputFormat::Hdr => ImageFormat::Hdr,
+        OutputFormat::Exr => ImageFormat::OpenExr,
+        OutputFormat::Ppm => unreachable!(),
+    };
+
+    dynamic
+        .save_with_format(path, image_format)
+        .map_err(|e| format!("Could not write {}: {e}", path.display()))
+}
+
+pub fn to_rgb8_image(image: &Framebuffer) -> RgbImage {
+    RgbImage::from_raw(image.width() as u32, image.height() as u32, image.to_rgb8())
+        .expect("Framebuffer size does not match its pixel data")
+}
+
+pub fn to_rgb32f_image(image: &Framebuffer) -> Rgb32FImage {
+    Rgb32FImage::from_raw(image.width() as u32, image.height() as u32, image.to_rgb_f32())
+        .expect("Framebuffer size does not match its pixel data")
+}
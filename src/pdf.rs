@@ -0,0 +1,143 @@
+use crate::hittable::Hittable;
+use crate::onb::Onb;
+use crate::sampler::Sampler;
+use crate::utils::PI;
+use crate::vec3::{dot, random_cosine_direction, random_unit_sphere, unit_vector, Point3, Vec3};
+
+// Probability density over directions, with a way to draw from it
+pub trait Pdf: Sync + Send {
+    fn value(&self, direction: Vec3) -> f64;
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3;
+}
+
+// Uniform over the whole sphere of directions
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        random_unit_sphere(sampler)
+    }
+}
+
+// Cosine weighted hemisphere around a normal
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(w: Vec3) -> Self {
+        CosinePdf { uvw: Onb::new(w) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: Vec3) -> f64 {
+        let cosine_theta = dot(unit_vector(direction), self.uvw.w());
+        f64::max(0.0, cosine_theta / PI)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.uvw.transform(random_cosine_direction(sampler))
+    }
+}
+
+// Directions from an origin towards the surface of some objects, placed as they are at time
+pub struct HittablePdf<'a> {
+    objects: &'a dyn Hittable,
+    origin: Point3,
+    time: f64,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(objects: &'a dyn Hittable, origin: Point3, time: f64) -> Self {
+        HittablePdf { objects, origin, time }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        self.objects.pdf_value(self.origin, direction, self.time)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.objects.random(self.origin, self.time, sampler)
+    }
+}
+
+// Even mix of two densities
+pub struct MixturePdf<'a> {
+    p: [&'a dyn Pdf; 2],
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(p0: &'a dyn Pdf, p1: &'a dyn Pdf) -> Self {
+        MixturePdf { p: [p0, p1] }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: Vec3) -> f64 {
+        0.5 * self.p[0].value(direction) + 0.5 * self.p[1].value(direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if sampler.get_1d() < 0.5 {
+            self.p[0].generate(sampler)
+        } else {
+            self.p[1].generate(sampler)
+        }
+    }
+}
+
+// Multiple importance sampling weight for a sample drawn from density f
+// when g could also have produced it
+pub fn power_heuristic(f: f64, g: f64) -> f64 {
+    let f2 = f * f;
+    let g2 = g * g;
+    if f2 + g2 > 0.0 { f2 / (f2 + g2) } else { 0.0 }
+}
+
+// Testing
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::IndependentSampler;
+
+    // Monte Carlo estimate of the integral of the pdf over the sphere
+    fn integral(pdf: &dyn Pdf) -> f64 {
+        let mut sampler = IndependentSampler::new(11);
+        let n = 200000;
+        let mut sum = 0.0;
+        for s in 0..n {
+            sampler.start_pixel_sample(0, 0, s);
+            sum += pdf.value(random_unit_sphere(&mut sampler)) * 4.0 * PI;
+        }
+        sum / n as f64
+    }
+
+    #[test]
+    fn test_pdfs_integrate_to_one() {
+        let cosine = CosinePdf::new(Vec3::new(1.0, 2.0, -0.5));
+        assert!((integral(&SpherePdf) - 1.0).abs() < 1e-9);
+        assert!((integral(&cosine) - 1.0).abs() < 0.01);
+        assert!((integral(&MixturePdf::new(&cosine, &SpherePdf)) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_cosine_samples_in_hemisphere() {
+        let normal = Vec3::new(0.0, -1.0, 1.0);
+        let pdf = CosinePdf::new(normal);
+        let mut sampler = IndependentSampler::new(3);
+        for s in 0..1000 {
+            sampler.start_pixel_sample(0, 0, s);
+            let d = pdf.generate(&mut sampler);
+            assert!((d.length() - 1.0).abs() < 1e-9);
+            assert!(dot(d, normal) >= 0.0);
+            assert!(pdf.value(d) > 0.0);
+        }
+    }
+}
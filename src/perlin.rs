@@ -1,4 +1,4 @@
-use crate::utils::random_int;
+use crate::utils::{random_int, Rng};
 use crate::vec3::{dot, unit_vector, Point3, Vec3};
 
 pub const POINT_COUNT: usize = 256;
@@ -11,15 +11,15 @@ pub struct Perlin {
 }
 
 impl Perlin {
-    pub fn new() -> Self {
+    pub fn new(rng: &mut Rng) -> Self {
         let mut randfloat = [Vec3::empty(); POINT_COUNT];
         for i in 0..POINT_COUNT {
-            randfloat[i] = unit_vector(Vec3::random_range(-1.0, 1.0));
+            randfloat[i] = unit_vector(Vec3::random_range(-1.0, 1.0, rng));
         }
 
-        let perm_x = Perlin::perlin_generate_perm();
-        let perm_y = Perlin::perlin_generate_perm();
-        let perm_z = Perlin::perlin_generate_perm();
+        let perm_x = Perlin::perlin_generate_perm(rng);
+        let perm_y = Perlin::perlin_generate_perm(rng);
+        let perm_z = Perlin::perlin_generate_perm(rng);
 
         Perlin {
             perm_x: perm_x,
@@ -67,19 +67,19 @@ impl Perlin {
         accum.abs()
     }
 
-    fn perlin_generate_perm() -> [i32; POINT_COUNT] {
+    fn perlin_generate_perm(rng: &mut Rng) -> [i32; POINT_COUNT] {
         let mut points = [0_i32; POINT_COUNT];
         for i in 0..POINT_COUNT {
             points[i] = i as i32;
         }
 
-        Perlin::permute(&mut points, POINT_COUNT);
+        Perlin::permute(&mut points, POINT_COUNT, rng);
         points
     }
 
-    fn permute(points: &mut [i32; 256], n: usize) {
+    fn permute(points: &mut [i32; 256], n: usize, rng: &mut Rng) {
         for i in (0..n-1).rev() {
-            let target = random_int(0, i as i32);
+            let target = random_int(0, i as i32, rng);
             let tmp = points[i];
             points[i] = target;
             points[target as usize] = tmp;
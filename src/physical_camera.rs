@@ -0,0 +1,103 @@
+use crate::camera::Camera;
+
+// Common sensor sizes, in millimeters
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SensorFormat {
+    // 36 x 24
+    #[default]
+    FullFrame,
+    // 23.6 x 15.6
+    ApsC,
+    // 17.3 x 13
+    MicroFourThirds,
+    // Width and height
+    Custom(f64, f64),
+}
+
+impl SensorFormat {
+    // Width and height in millimeters
+    pub fn size(&self) -> (f64, f64) {
+        match *self {
+            SensorFormat::FullFrame => (36.0, 24.0),
+            SensorFormat::ApsC => (23.6, 15.6),
+            SensorFormat::MicroFourThirds => (17.3, 13.0),
+            SensorFormat::Custom(width, height) => (width, height),
+        }
+    }
+}
+
+// Camera settings the way a photographer gives them. apply turns them into the field of
+// view, aspect ratio, defocus angle and exposure of a Camera
+#[derive(Clone, Debug)]
+pub struct PhysicalCamera {
+    pub focal_length: f64, // Millimeters
+    pub sensor: SensorFormat,
+    pub f_number: f64,
+    pub iso: f64,
+    // Seconds. Only sets the exposure, the motion blur still spans shutter_open to shutter_close
+    pub shutter_time: f64,
+    pub focus_dist: f64, // In scene units
+    pub units_per_meter: f64,
+}
+
+impl PhysicalCamera {
+    // A 50mm lens on a full frame sensor, exposed by the sunny 16 rule
+    pub fn new() -> Self {
+        PhysicalCamera {
+            focal_length: 50.0,
+            sensor: SensorFormat::FullFrame,
+            f_number: 16.0,
+            iso: 100.0,
+            shutter_time: 0.01,
+            focus_dist: 10.0,
+            units_per_meter: 1.0,
+        }
+    }
+
+    // Vertical field of view in degrees, covering the height of the sensor
+    pub fn vfov(&self) -> f64 {
+        let (_, height) = self.sensor.size();
+        2.0 * f64::atan(height / (2.0 * self.focal_length)).to_degrees()
+    }
+
+    // Radius of the aperture in scene units
+    pub fn aperture_radius(&self) -> f64 {
+        0.5 * self.focal_length / self.f_number / 1000.0 * self.units_per_meter
+    }
+
+    // Sensor exposure relative to the sunny 16 rule, f/16 at 1/ISO seconds, so a scene lit
+    // like daylight by radiances around 1 comes out at its usual brightness
+    pub fn exposure(&self) -> f64 {
+        256.0 * self.shutter_time * self.iso / (self.f_number * self.f_number)
+    }
+
+    pub fn apply(&self, cam: &mut Camera) -> Result<(), String> {
+        let (width, height) = self.sensor.size();
+        let settings = [
+            ("Focal length", self.focal_length),
+            ("Sensor width", width),
+            ("Sensor height", height),
+            ("F-number", self.f_number),
+            ("ISO", self.iso),
+            ("Shutter time", self.shutter_time),
+            ("Focus distance", self.focus_dist),
+            ("Units per meter", self.units_per_meter),
+        ];
+        if let Some((name, _)) = settings.iter().find(|(_, value)| !value.is_finite() || *value <= 0.0) {
+            return Err(format!("{name} must be positive."));
+        }
+
+        cam.aspect_ratio = width / height;
+        cam.vfov = self.vfov();
+        cam.focus_dist = self.focus_dist;
+        cam.defocus_angle = 2.0 * f64::atan(self.aperture_radius() / self.focus_dist).to_degrees();
+        cam.exposure = self.exposure();
+        Ok(())
+    }
+}
+
+impl Default for PhysicalCamera {
+    fn default() -> Self {
+        PhysicalCamera::new()
+    }
+}
@@ -6,6 +6,8 @@ use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::utils::INFINITY;
 use crate::vec3::{cross, dot, unit_vector, Point3, Vec3};
 
 pub struct Quadrilateral {
@@ -15,6 +17,7 @@ pub struct Quadrilateral {
     normal: Vec3,
     offset: f64,
     w: Vec3,
+    area: f64,
     bbox: AABB,
     mat: Arc<dyn Material + Sync + Send>,
 }
@@ -35,6 +38,7 @@ impl Quadrilateral {
             normal: normal,
             offset: offset,
             w: n / dot(n, n),
+            area: n.length(),
             bbox: AABB::from_bboxes(&bbox_diagonal1, &bbox_diagonal2),
             mat: mat,
         }
@@ -89,6 +93,25 @@ impl Hittable for Quadrilateral {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, _time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit(&Ray::new(origin, direction), &mut Interval::new(0.001, INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        // Convert the uniform area density to solid angle as seen from origin
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = f64::abs(dot(direction, rec.normal) / direction.length());
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.get_2d();
+        let p = self.q + (a * self.u) + (b * self.v);
+        p - origin
+    }
 }
 
 pub fn create_box(a: Point3, b: Point3, mat: Arc<dyn Material>) -> Arc<dyn Hittable> {
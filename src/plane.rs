@@ -41,7 +41,8 @@ impl Hittable for Plane {
         return true;
     }
 
+    // Unbounded, BVHs set planes apart from the objects they sort into their tree
     fn bounding_box(&self) -> AABB {
-        AABB::EMPTY
+        AABB::UNIVERSE
     }
 }
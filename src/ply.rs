@@ -0,0 +1,376 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::color::Color;
+use crate::mesh::MeshData;
+use crate::vec3::{Point3, Vec3};
+
+// Stanford PLY import, ASCII and binary in either byte order.
+//
+// The vertex element provides x/y/z, and optionally nx/ny/nz, u/v (or s/t) and
+// red/green/blue colors. Faces are lists of vertex indices, triangulated as fans.
+// Other elements are read and skipped
+
+#[derive(Debug)]
+pub enum PlyError {
+    // The file could not be read
+    Io { path: PathBuf, message: String },
+    // Malformed header, with the 1-based line it is on
+    Header { path: PathBuf, line: usize, message: String },
+    // The element data does not match the header
+    Data { path: PathBuf, message: String },
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlyError::Io { path, message } => write!(f, "{}: {message}", path.display()),
+            PlyError::Header { path, line, message } => write!(f, "{}:{line}: {message}", path.display()),
+            PlyError::Data { path, message } => write!(f, "{}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+pub fn load_ply<P: AsRef<Path>>(path: P) -> Result<MeshData, PlyError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| PlyError::Io { path: path.to_path_buf(), message: e.to_string() })?;
+    parse_ply(&bytes, path)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    // Integer colors use the full range of their type
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::U8 => 1.0 / 255.0,
+            Scalar::U16 => 1.0 / 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Property {
+    Scalar { name: String, ty: Scalar },
+    List { name: String, count: Scalar, item: Scalar },
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar { name, .. } | Property::List { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+// Parses the header, returning it with the offset of the first data byte
+fn parse_header(bytes: &[u8], path: &Path) -> Result<(Header, usize), PlyError> {
+    let error = |line: usize, message: String| PlyError::Header { path: path.to_path_buf(), line, message };
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    let mut number = 0;
+
+    loop {
+        let end = match bytes[offset..].iter().position(|&b| b == b'\n') {
+            Some(end) => offset + end,
+            None => return Err(error(number + 1, "Missing 'end_header'".to_string())),
+        };
+        let text = String::from_utf8_lossy(&bytes[offset..end]);
+        let text = text.trim();
+        offset = end + 1;
+        number += 1;
+
+        let mut args = text.split_whitespace();
+        let keyword = args.next().unwrap_or("");
+        if number == 1 {
+            if keyword != "ply" {
+                return Err(error(number, "Not a PLY file".to_string()));
+            }
+            continue;
+        }
+
+        match keyword {
+            "format" => {
+                format = match args.next() {
+                    Some("ascii") => Some(Format::Ascii),
+                    Some("binary_little_endian") => Some(Format::BinaryLittleEndian),
+                    Some("binary_big_endian") => Some(Format::BinaryBigEndian),
+                    other => return Err(error(number, format!("Unknown format '{}'", other.unwrap_or("")))),
+                }
+            }
+            "element" => {
+                let name = args.next().unwrap_or("");
+                let count = args.next().and_then(|c| c.parse::<usize>().ok());
+                match count {
+                    Some(count) if !name.is_empty() => {
+                        elements.push(Element { name: name.to_string(), count, properties: Vec::new() })
+                    }
+                    _ => return Err(error(number, format!("Invalid element '{text}'"))),
+                }
+            }
+            "property" => {
+                let tokens: Vec<&str> = args.collect();
+                let property = match tokens.as_slice() {
+                    ["list", count, item, name] => match (Scalar::from_name(count), Scalar::from_name(item)) {
+                        (Some(count), Some(item)) => Property::List { name: name.to_string(), count, item },
+                        _ => return Err(error(number, format!("Unknown list type in '{text}'"))),
+                    },
+                    [ty, name] => match Scalar::from_name(ty) {
+                        Some(ty) => Property::Scalar { name: name.to_string(), ty },
+                        None => return Err(error(number, format!("Unknown property type '{ty}'"))),
+                    },
+                    _ => return Err(error(number, format!("Invalid property '{text}'"))),
+                };
+                match elements.last_mut() {
+                    Some(element) => element.properties.push(property),
+                    None => return Err(error(number, "Property before any element".to_string())),
+                }
+            }
+            "end_header" => break,
+            // Comments and object information
+            _ => {}
+        }
+    }
+
+    match format {
+        Some(format) => Ok((Header { format, elements }, offset)),
+        None => Err(error(number, "Missing 'format' line".to_string())),
+    }
+}
+
+// Reads the values of the body one at a time, whatever the encoding
+struct DataReader<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    offset: usize,
+    tokens: std::str::SplitWhitespace<'a>,
+}
+
+impl<'a> DataReader<'a> {
+    fn new(format: Format, bytes: &'a [u8], text: &'a str) -> Self {
+        DataReader { format, bytes, offset: 0, tokens: text.split_whitespace() }
+    }
+
+    fn read(&mut self, ty: Scalar) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            let token = self.tokens.next().ok_or("Unexpected end of data")?;
+            return token.parse::<f64>().map_err(|_| format!("Invalid number '{token}'"));
+        }
+
+        let size = ty.size();
+        if self.offset + size > self.bytes.len() {
+            return Err("Unexpected end of data".to_string());
+        }
+        let mut raw = [0u8; 8];
+        raw[..size].copy_from_slice(&self.bytes[self.offset..self.offset + size]);
+        if self.format == Format::BinaryBigEndian {
+            raw[..size].reverse();
+        }
+        self.offset += size;
+
+        Ok(match ty {
+            Scalar::I8 => raw[0] as i8 as f64,
+            Scalar::U8 => raw[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(raw),
+        })
+    }
+}
+
+// Parses a whole PLY file. path names the file in errors
+pub fn parse_ply(bytes: &[u8], path: &Path) -> Result<MeshData, PlyError> {
+    let (header, offset) = parse_header(bytes, path)?;
+    let data_error = |message: String| PlyError::Data { path: path.to_path_buf(), message };
+
+    let body = &bytes[offset..];
+    let text = match header.format {
+        Format::Ascii => std::str::from_utf8(body).map_err(|_| data_error("ASCII data is not valid text".to_string()))?,
+        _ => "",
+    };
+    let mut reader = DataReader::new(header.format, body, text);
+
+    let mut data = MeshData::default();
+    for element in header.elements.iter() {
+        match element.name.as_str() {
+            "vertex" => read_vertices(element, &mut reader, &mut data),
+            "face" => read_faces(element, &mut reader, &mut data),
+            _ => skip_element(element, &mut reader),
+        }
+        .map_err(|message| data_error(format!("In element '{}': {message}", element.name)))?;
+    }
+
+    if let Some(face) = data.indices.iter().position(|f| f.iter().any(|&i| i >= data.positions.len())) {
+        return Err(data_error(format!("Face {face} uses a vertex out of range")));
+    }
+
+    Ok(data)
+}
+
+// Position of a named scalar property among the element's properties
+fn find(element: &Element, names: &[&str]) -> Option<usize> {
+    element.properties.iter().position(|p| matches!(p, Property::Scalar { .. }) && names.contains(&p.name()))
+}
+
+fn read_vertices(element: &Element, reader: &mut DataReader, data: &mut MeshData) -> Result<(), String> {
+    let xyz = [find(element, &["x"]), find(element, &["y"]), find(element, &["z"])];
+    let [x, y, z] = match xyz {
+        [Some(x), Some(y), Some(z)] => [x, y, z],
+        _ => return Err("Vertices need x, y and z".to_string()),
+    };
+    let normal = [find(element, &["nx"]), find(element, &["ny"]), find(element, &["nz"])];
+    let uv = [find(element, &["u", "s", "texture_u"]), find(element, &["v", "t", "texture_v"])];
+    let color = [
+        find(element, &["red", "r", "diffuse_red"]),
+        find(element, &["green", "g", "diffuse_green"]),
+        find(element, &["blue", "b", "diffuse_blue"]),
+    ];
+
+    let mut values = vec![0.0; element.properties.len()];
+    for _ in 0..element.count {
+        for (k, property) in element.properties.iter().enumerate() {
+            values[k] = match property {
+                Property::Scalar { ty, .. } => reader.read(*ty)?,
+                Property::List { count, item, .. } => {
+                    for _ in 0..reader.read(*count)? as usize {
+                        reader.read(*item)?;
+                    }
+                    0.0
+                }
+            };
+        }
+
+        data.positions.push(Point3::new(values[x], values[y], values[z]));
+        if let [Some(nx), Some(ny), Some(nz)] = normal {
+            data.normals.push(Vec3::new(values[nx], values[ny], values[nz]));
+        }
+        if let [Some(u), Some(v)] = uv {
+            data.uvs.push([values[u], values[v]]);
+        }
+        if let [Some(r), Some(g), Some(b)] = color {
+            let scale = |i: usize| match &element.properties[i] {
+                Property::Scalar { ty, .. } => values[i] * ty.color_scale(),
+                _ => values[i],
+            };
+            data.colors.push(Color::new(scale(r), scale(g), scale(b)));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_faces(element: &Element, reader: &mut DataReader, data: &mut MeshData) -> Result<(), String> {
+    let list = element
+        .properties
+        .iter()
+        .position(|p| matches!(p, Property::List { .. }) && (p.name() == "vertex_indices" || p.name() == "vertex_index"))
+        .ok_or("Faces need a vertex_indices list")?;
+
+    let mut polygon = Vec::new();
+    for _ in 0..element.count {
+        for (k, property) in element.properties.iter().enumerate() {
+            match property {
+                Property::Scalar { ty, .. } => {
+                    reader.read(*ty)?;
+                }
+                Property::List { count, item, .. } => {
+                    let n = reader.read(*count)? as usize;
+                    polygon.clear();
+                    for _ in 0..n {
+                        polygon.push(reader.read(*item)?);
+                    }
+                    if k == list {
+                        if polygon.iter().any(|&i| i < 0.0) {
+                            return Err("Negative vertex index".to_string());
+                        }
+                        for i in 1..n.saturating_sub(1) {
+                            data.indices.push([polygon[0] as usize, polygon[i] as usize, polygon[i + 1] as usize]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn skip_element(element: &Element, reader: &mut DataReader) -> Result<(), String> {
+    for _ in 0..element.count {
+        for property in element.properties.iter() {
+            match property {
+                Property::Scalar { ty, .. } => {
+                    reader.read(*ty)?;
+                }
+                Property::List { count, item, .. } => {
+                    for _ in 0..reader.read(*count)? as usize {
+                        reader.read(*item)?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
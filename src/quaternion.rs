@@ -0,0 +1,83 @@
+use crate::matrix::Matrix4;
+use crate::utils::degrees_to_radians;
+use crate::vec3::{unit_vector, Vec3};
+
+// Unit quaternion of a rotation, w + xi + yj + zk. Used to interpolate rotations, which
+// matrices can't do without shearing in between
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion { w: 1.0, x: 0.0, y: 0.0, z: 0.0 };
+
+    // Same sense as Matrix4::rotate, counterclockwise in degrees looking down the axis
+    pub fn from_axis_angle(axis: Vec3, angle: f64) -> Self {
+        let k = unit_vector(axis);
+        let (sin, cos) = (0.5 * degrees_to_radians(angle)).sin_cos();
+        Quaternion { w: cos, x: sin * k.x(), y: sin * k.y(), z: sin * k.z() }
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    fn scaled(&self, s: f64) -> Self {
+        Quaternion { w: s * self.w, x: s * self.x, y: s * self.y, z: s * self.z }
+    }
+
+    fn add(&self, other: &Quaternion) -> Self {
+        Quaternion { w: self.w + other.w, x: self.x + other.x, y: self.y + other.y, z: self.z + other.z }
+    }
+
+    fn normalized(&self) -> Self {
+        self.scaled(1.0 / self.dot(self).sqrt())
+    }
+
+    // Radians the rotation turns by between self and other, along the shortest way
+    pub fn angle_to(&self, other: &Quaternion) -> f64 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    // Rotation at t between self (t = 0) and other (t = 1), turning at a constant rate
+    // the shortest way round
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Self {
+        // q and -q are the same rotation, pick the one on the near side
+        let mut cos = self.dot(other);
+        let other = if cos < 0.0 {
+            cos = -cos;
+            other.scaled(-1.0)
+        } else {
+            *other
+        };
+
+        // Nearly equal rotations, where the sine below vanishes
+        if cos > 0.9995 {
+            return self.scaled(1.0 - t).add(&other.scaled(t)).normalized();
+        }
+
+        let theta = cos.acos();
+        let sin = theta.sin();
+        self.scaled(((1.0 - t) * theta).sin() / sin).add(&other.scaled((t * theta).sin() / sin))
+    }
+
+    pub fn to_matrix(&self) -> Matrix4 {
+        let Quaternion { w, x, y, z } = *self;
+        Matrix4::new([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quaternion::IDENTITY
+    }
+}
@@ -0,0 +1,433 @@
+use crate::utils::{hash, Rng};
+
+// Source of sample values for one pixel sample at a time. Every call to
+// get_1d/get_2d consumes the next dimension of the current sample, so callers
+// must request dimensions in the same order for every sample
+pub trait Sampler {
+    // Restarts the dimension count for sample `index` of pixel (i, j)
+    fn start_pixel_sample(&mut self, i: i32, j: i32, index: i32);
+
+    fn get_1d(&mut self) -> f64;
+
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SamplerType {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerType {
+    pub fn build(&self, samples_per_pixel: i32, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerType::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerType::Stratified => Box::new(StratifiedSampler::new(samples_per_pixel, seed)),
+            SamplerType::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerType::Sobol => Box::new(SobolSampler::new(samples_per_pixel, seed)),
+        }
+    }
+}
+
+// Uniform random values, with no relation between samples
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Rng,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        IndependentSampler { seed, rng: Rng::new(seed) }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, i: i32, j: i32, index: i32) {
+        self.rng = Rng::for_sample(self.seed, i, j, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.uniform()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.rng.uniform(), self.rng.uniform())
+    }
+}
+
+// Jittered sampling. The samples of a pixel are spread over as many strata as
+// there are samples, with the strata shuffled independently per dimension
+pub struct StratifiedSampler {
+    seed: u64,
+    samples_per_pixel: u32,
+    x_strata: u32,
+    y_strata: u32,
+    pixel: (i32, i32),
+    index: u32,
+    dimension: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: i32, seed: u64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1) as u32;
+
+        // Largest divisor not above the square root gives the squarest grid
+        let mut x_strata = (samples_per_pixel as f64).sqrt() as u32;
+        while !samples_per_pixel.is_multiple_of(x_strata) {
+            x_strata -= 1;
+        }
+
+        StratifiedSampler {
+            seed,
+            samples_per_pixel,
+            x_strata,
+            y_strata: samples_per_pixel / x_strata,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    // Stratum of the current sample for the current dimension. Samples past
+    // samples_per_pixel start a new, differently shuffled, set of strata
+    fn stratum(&self) -> (u32, u64) {
+        let epoch = (self.index / self.samples_per_pixel) as u64;
+        let key = self.pixel_hash(&[self.dimension, epoch]);
+        let stratum = permutation_element(self.index % self.samples_per_pixel, self.samples_per_pixel, key as u32);
+        (stratum, self.pixel_hash(&[self.dimension, self.index as u64]))
+    }
+
+    fn pixel_hash(&self, values: &[u64]) -> u64 {
+        let mut h = hash(&[self.seed, self.pixel.0 as u64, self.pixel.1 as u64]);
+        for &v in values {
+            h = hash(&[h, v]);
+        }
+        h
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, i: i32, j: i32, index: i32) {
+        self.pixel = (i, j);
+        self.index = index as u32;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (stratum, jitter) = self.stratum();
+        self.dimension += 1;
+
+        (stratum as f64 + hash_to_unit(jitter)) / self.samples_per_pixel as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (stratum, jitter) = self.stratum();
+        self.dimension += 2;
+
+        let x = stratum % self.x_strata;
+        let y = stratum / self.x_strata;
+        (
+            (x as f64 + hash_to_unit(jitter)) / self.x_strata as f64,
+            (y as f64 + hash_to_unit(hash(&[jitter]))) / self.y_strata as f64,
+        )
+    }
+}
+
+// Halton low-discrepancy sequence. Dimension d uses the radical inverse in the
+// d-th prime base, decorrelated between pixels with a random toroidal shift
+pub struct HaltonSampler {
+    seed: u64,
+    primes: Vec<u32>,
+    pixel: (i32, i32),
+    index: u64,
+    dimension: usize,
+    fallback: Rng,
+}
+
+impl HaltonSampler {
+    // Past this many dimensions the sampler falls back to independent values
+    pub const MAX_DIMENSIONS: usize = 256;
+
+    pub fn new(seed: u64) -> Self {
+        HaltonSampler {
+            seed,
+            primes: first_primes(HaltonSampler::MAX_DIMENSIONS),
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+            fallback: Rng::new(seed),
+        }
+    }
+
+    fn sample_dimension(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+
+        if dimension >= self.primes.len() {
+            return self.fallback.uniform();
+        }
+
+        let value = radical_inverse(self.primes[dimension] as u64, self.index);
+        let shift = hash_to_unit(hash(&[self.seed, self.pixel.0 as u64, self.pixel.1 as u64, dimension as u64]));
+
+        let shifted = value + shift;
+        if shifted >= 1.0 { shifted - 1.0 } else { shifted }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, i: i32, j: i32, index: i32) {
+        self.pixel = (i, j);
+        self.index = index as u64;
+        self.dimension = 0;
+        self.fallback = Rng::for_sample(self.seed, i, j, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.sample_dimension()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let x = self.sample_dimension();
+        let y = self.sample_dimension();
+        (x, y)
+    }
+}
+
+// Padded Sobol sampling: every 1D or 2D request draws from the first one or two
+// dimensions of the Sobol sequence, which form a (0,2)-sequence. Each request
+// shuffles the pixel's samples and applies its own nested uniform (Owen)
+// scramble, following Burley, "Practical Hash-based Owen Scrambling" (JCGT 2020).
+// Sample counts that are powers of two give the best distribution
+pub struct SobolSampler {
+    seed: u64,
+    samples_per_pixel: u32,
+    pixel: (i32, i32),
+    index: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(samples_per_pixel: i32, seed: u64) -> Self {
+        SobolSampler {
+            seed,
+            samples_per_pixel: samples_per_pixel.max(1) as u32,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn dimension_hash(&mut self) -> u64 {
+        let h = hash(&[self.seed, self.pixel.0 as u64, self.pixel.1 as u64, self.dimension]);
+        self.dimension += 1;
+        h
+    }
+
+    // Shuffles the samples within each block of samples_per_pixel indices
+    fn shuffled_index(&self, h: u64) -> u32 {
+        let block = self.index / self.samples_per_pixel;
+        let offset = permutation_element(self.index % self.samples_per_pixel, self.samples_per_pixel, h as u32);
+        block * self.samples_per_pixel + offset
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, i: i32, j: i32, index: i32) {
+        self.pixel = (i, j);
+        self.index = index as u32;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let h = self.dimension_hash();
+        let index = self.shuffled_index(h);
+
+        let x = nested_uniform_scramble(index.reverse_bits(), (h >> 32) as u32);
+        bits_to_unit(x)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let h = self.dimension_hash();
+        let index = self.shuffled_index(h);
+
+        let (x, y) = sobol_02(index);
+        let hx = (h >> 32) as u32;
+        let hy = hash(&[h]) as u32;
+        (
+            bits_to_unit(nested_uniform_scramble(x, hx)),
+            bits_to_unit(nested_uniform_scramble(y, hy)),
+        )
+    }
+}
+
+// First two Sobol dimensions: van der Corput and the Pascal matrix generator
+fn sobol_02(mut index: u32) -> (u32, u32) {
+    let mut x = 0u32;
+    let mut y = 0u32;
+    let mut vx = 1u32 << 31;
+    let mut vy = 1u32 << 31;
+
+    while index != 0 {
+        if index & 1 == 1 {
+            x ^= vx;
+            y ^= vy;
+        }
+        index >>= 1;
+        vx >>= 1;
+        vy ^= vy >> 1;
+    }
+
+    (x, y)
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+// Element `i` of a random permutation of 0..len selected by `p`, without
+// building the permutation (Kensler, "Correlated Multi-Jittered Sampling")
+fn permutation_element(mut i: u32, len: u32, p: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            break;
+        }
+    }
+
+    i.wrapping_add(p) % len
+}
+
+fn radical_inverse(base: u64, mut index: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_power = 1.0;
+    let mut reversed = 0u64;
+
+    while index > 0 {
+        let next = index / base;
+        let digit = index - next * base;
+        reversed = reversed * base + digit;
+        inv_base_power *= inv_base;
+        index = next;
+    }
+
+    f64::min(reversed as f64 * inv_base_power, 1.0 - f64::EPSILON)
+}
+
+fn first_primes(n: usize) -> Vec<u32> {
+    let mut primes: Vec<u32> = Vec::with_capacity(n);
+    let mut candidate = 2u32;
+
+    while primes.len() < n {
+        if primes.iter().take_while(|&&p| p * p <= candidate).all(|&p| !candidate.is_multiple_of(p)) {
+            primes.push(candidate);
+        }
+        candidate += 1;
+    }
+
+    primes
+}
+
+fn bits_to_unit(x: u32) -> f64 {
+    x as f64 / 4294967296.0
+}
+
+fn hash_to_unit(h: u64) -> f64 {
+    (h >> 11) as f64 / 9007199254740992.0
+}
+
+// Testing
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel_samples(sampler: &mut dyn Sampler, n: i32) -> Vec<(f64, f64)> {
+        (0..n)
+            .map(|s| {
+                sampler.start_pixel_sample(3, 5, s);
+                sampler.get_2d()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_samples_in_unit_square() {
+        for kind in [SamplerType::Independent, SamplerType::Stratified, SamplerType::Halton, SamplerType::Sobol] {
+            let mut sampler = kind.build(16, 9);
+            for s in 0..64 {
+                sampler.start_pixel_sample(1, 2, s);
+                for _ in 0..300 {
+                    let u = sampler.get_1d();
+                    let (x, y) = sampler.get_2d();
+                    assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stratified_covers_strata() {
+        let mut sampler = StratifiedSampler::new(16, 1);
+        let mut cells = [false; 16];
+        for (x, y) in pixel_samples(&mut sampler, 16) {
+            cells[(y * 4.0) as usize * 4 + (x * 4.0) as usize] = true;
+        }
+        assert!(cells.iter().all(|&c| c));
+    }
+
+    #[test]
+    fn test_sobol_is_02_net() {
+        // Every elementary interval of area 1/16 holds exactly one point
+        let mut sampler = SobolSampler::new(16, 5);
+        let points = pixel_samples(&mut sampler, 16);
+        for (nx, ny) in [(16, 1), (8, 2), (4, 4), (2, 8), (1, 16)] {
+            let mut cells = [0; 16];
+            for &(x, y) in &points {
+                cells[(y * ny as f64) as usize * nx + (x * nx as f64) as usize] += 1;
+            }
+            assert!(cells.iter().all(|&c| c == 1));
+        }
+    }
+
+    #[test]
+    fn test_radical_inverse() {
+        assert_eq!(radical_inverse(2, 1), 0.5);
+        assert_eq!(radical_inverse(2, 3), 0.75);
+        assert!((radical_inverse(3, 5) - 7.0 / 9.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,828 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use toml_edit::{ImDocument, Item, TableLike};
+
+use crate::bvh::BVH;
+use crate::camera::{Camera, Projection};
+use crate::camera_model::FisheyeMapping;
+use crate::constant_medium::ConstantMedium;
+use crate::environment::EnvironmentLight;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::integrator::{
+    AlbedoIntegrator, AmbientOcclusionIntegrator, DirectLightingIntegrator, Integrator, NormalIntegrator,
+    PathIntegrator, UvIntegrator,
+};
+use crate::material::{Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal};
+use crate::physical_camera::{PhysicalCamera, SensorFormat};
+use crate::planar::{create_box, Quadrilateral};
+use crate::plane::Plane;
+use crate::sampler::SamplerType;
+use crate::shutter::ShutterCurve;
+use crate::sphere::Sphere;
+use crate::texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor, Texture};
+use crate::quaternion::Quaternion;
+use crate::transformations::{AnimatedTransform, Keyframe, RotateY, Transform, Translate};
+use crate::utils::Rng;
+use crate::vec3::Vec3;
+
+// TOML scene description.
+//
+//   seed = 0                  random seed of the noise textures
+//   [camera]                  any Camera field, plus integrator, ao_distance and sampler by name.
+//                             The image is 400 pixels wide at 16:9 unless given otherwise.
+//                             shutter_curve is box, triangle or an array of efficiencies,
+//                             projection is perspective, orthographic, equirectangular or
+//                             fisheye, fisheye_mapping is equidistant or equisolid.
+//                             focal_length (mm) with sensor, f_number, iso, shutter_time and
+//                             units_per_meter replace vfov, defocus_angle and exposure.
+//                             focus_pixel = [x, y] focuses on what that pixel sees
+//   [environment]             path, intensity, rotation
+//   [textures.<name>]         type = solid, checker, image or noise
+//   [materials.<name>]        type = lambertian, metal, dielectric, diffuse_light or isotropic
+//   [[objects]]               type = sphere, quad, box, plane, constant_medium, translate, rotate_y,
+//                             transform, animated_transform or bvh
+//
+// Vectors and colors are arrays of three numbers. Materials name a texture or take a color, and
+// objects name a material or define one inline. Names must be defined before they are used.
+// Wrappers hold their child in `object`, bvh groups in `objects`. Top level spheres, quads
+// and boxes, or wrappers of them, with `light = true` are also sampled as lights. A transform
+// scales its object (a number or one factor per axis), rotates it by `angle` degrees about
+// `axis` and then moves it by `translate`.
+// An animated_transform moves its object through `keyframes`, each a placement like the
+// above at a `time`. Moving spheres go from `center` at `time` (0) to `center2` at `time2` (1).
+// File paths are relative to the scene file
+
+#[derive(Debug)]
+pub enum SceneError {
+    // A file could not be read
+    Io { path: PathBuf, message: String },
+    // Invalid TOML or scene description, with the 1-based line it is on
+    Parse { path: PathBuf, line: usize, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io { path, message } => write!(f, "{}: {message}", path.display()),
+            SceneError::Parse { path, line, message } => write!(f, "{}:{line}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+// Camera ready to render the world. The lights are already registered on the camera
+pub struct Scene {
+    pub camera: Camera,
+    pub world: HittableList,
+    // Named textures and materials. Inline materials are not listed
+    pub textures: HashMap<String, Arc<dyn Texture>>,
+    pub materials: HashMap<String, Arc<dyn Material + Sync + Send>>,
+    // Spheres, quads and planes in the world, counting the six faces of every box
+    pub primitive_count: usize,
+}
+
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let source =
+        fs::read_to_string(path).map_err(|e| SceneError::Io { path: path.to_path_buf(), message: e.to_string() })?;
+    parse_scene(&source, path)
+}
+
+// Parses a whole scene file. path names the file in errors and locates the textures
+pub fn parse_scene(source: &str, path: &Path) -> Result<Scene, SceneError> {
+    let document = ImDocument::parse(source).map_err(|e| SceneError::Parse {
+        path: path.to_path_buf(),
+        line: line_of(source, e.span().map_or(0, |s| s.start)),
+        message: e.message().trim_end().to_string(),
+    })?;
+
+    let mut parser = SceneParser {
+        source,
+        path,
+        base_dir: path.parent().unwrap_or(Path::new("")),
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        rng: Rng::new(0),
+        primitive_count: 0,
+    };
+    parser.parse(Section { table: document.as_table(), offset: 0 })
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+// A table of the file, with the position errors about the table as a whole point to
+#[derive(Clone, Copy)]
+struct Section<'a> {
+    table: &'a dyn TableLike,
+    offset: usize,
+}
+
+impl<'a> Section<'a> {
+    // The value of key with the position of its value, or of its key when the value has none
+    fn get(&self, key: &str) -> Option<(&'a Item, usize)> {
+        let (k, item) = self.table.get_key_value(key)?;
+        let offset = item.span().or_else(|| k.span()).map_or(self.offset, |s| s.start);
+        Some((item, offset))
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (&'a str, &'a Item, usize)> + '_ {
+        self.table.iter().map(|(key, item)| {
+            let offset = self.table.key(key).and_then(|k| k.span()).map_or(self.offset, |s| s.start);
+            (key, item, offset)
+        })
+    }
+}
+
+struct SceneParser<'a> {
+    source: &'a str,
+    path: &'a Path,
+    base_dir: &'a Path,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material + Sync + Send>>,
+    rng: Rng,
+    primitive_count: usize,
+}
+
+impl<'a> SceneParser<'a> {
+    fn error(&self, offset: usize, message: String) -> SceneError {
+        SceneError::Parse { path: self.path.to_path_buf(), line: line_of(self.source, offset), message }
+    }
+
+    // Rejects misspelled keys instead of silently using defaults
+    fn check_keys(&self, section: Section, kind: &str, allowed: &[&str]) -> Result<(), SceneError> {
+        match section.entries().find(|(key, _, _)| !allowed.contains(key)) {
+            Some((key, _, offset)) => Err(self.error(offset, format!("Unknown key '{key}' in {kind}"))),
+            None => Ok(()),
+        }
+    }
+
+    fn value<T>(
+        &self,
+        section: Section<'a>,
+        key: &str,
+        expected: &str,
+        convert: impl Fn(&'a Item) -> Option<T>,
+    ) -> Result<Option<T>, SceneError> {
+        match section.get(key) {
+            Some((item, offset)) => match convert(item) {
+                Some(value) => Ok(Some(value)),
+                None => Err(self.error(offset, format!("'{key}' must be {expected}"))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn required<T>(&self, section: Section, key: &str, value: Option<T>) -> Result<T, SceneError> {
+        value.ok_or_else(|| self.error(section.offset, format!("Missing '{key}'")))
+    }
+
+    fn number(&self, section: Section, key: &str) -> Result<Option<f64>, SceneError> {
+        self.value(section, key, "a number", as_number)
+    }
+
+    fn integer(&self, section: Section, key: &str) -> Result<Option<i64>, SceneError> {
+        self.value(section, key, "an integer", |item| item.as_integer())
+    }
+
+    fn boolean(&self, section: Section, key: &str) -> Result<Option<bool>, SceneError> {
+        self.value(section, key, "true or false", |item| item.as_bool())
+    }
+
+    fn string(&self, section: Section<'a>, key: &str) -> Result<Option<&'a str>, SceneError> {
+        self.value(section, key, "a string", |item| item.as_str())
+    }
+
+    fn vector(&self, section: Section, key: &str) -> Result<Option<Vec3>, SceneError> {
+        self.value(section, key, "an array of 3 numbers", |item| {
+            let values: Vec<f64> =
+                item.as_array()?.iter().map(|v| v.as_float().or(v.as_integer().map(|i| i as f64))).collect::<Option<_>>()?;
+            (values.len() == 3).then(|| Vec3::new(values[0], values[1], values[2]))
+        })
+    }
+
+    fn required_number(&self, section: Section, key: &str) -> Result<f64, SceneError> {
+        let value = self.number(section, key)?;
+        self.required(section, key, value)
+    }
+
+    fn required_vector(&self, section: Section, key: &str) -> Result<Vec3, SceneError> {
+        let value = self.vector(section, key)?;
+        self.required(section, key, value)
+    }
+
+    fn table(&self, item: &'a Item, offset: usize, what: &str) -> Result<Section<'a>, SceneError> {
+        match item.as_table_like() {
+            Some(table) => Ok(Section { table, offset }),
+            None => Err(self.error(offset, format!("{what} must be a table"))),
+        }
+    }
+
+    fn kind(&self, section: Section<'a>) -> Result<&'a str, SceneError> {
+        let kind = self.string(section, "type")?;
+        self.required(section, "type", kind)
+    }
+
+    fn parse(&mut self, root: Section<'a>) -> Result<Scene, SceneError> {
+        self.check_keys(root, "the scene", &["seed", "camera", "environment", "textures", "materials", "objects"])?;
+
+        if let Some(seed) = self.integer(root, "seed")? {
+            self.rng = Rng::new(seed as u64);
+        }
+
+        // Camera::new leaves the image size to the caller
+        let mut camera = Camera::new();
+        camera.aspect_ratio = 16.0 / 9.0;
+        camera.image_width = 400;
+        if let Some((item, offset)) = root.get("camera") {
+            self.camera(self.table(item, offset, "camera")?, &mut camera)?;
+        }
+        if let Some((item, offset)) = root.get("environment") {
+            let environment = self.environment(self.table(item, offset, "environment")?)?;
+            camera.environment = Some(Arc::new(environment));
+        }
+
+        if let Some((item, offset)) = root.get("textures") {
+            for (name, item, offset) in self.table(item, offset, "textures")?.entries() {
+                let texture = self.texture(self.table(item, offset, "A texture")?)?;
+                self.textures.insert(name.to_string(), texture);
+            }
+        }
+        if let Some((item, offset)) = root.get("materials") {
+            for (name, item, offset) in self.table(item, offset, "materials")?.entries() {
+                let material = self.material(self.table(item, offset, "A material")?)?;
+                self.materials.insert(name.to_string(), material);
+            }
+        }
+
+        let mut world = HittableList::new();
+        if let Some((item, offset)) = root.get("objects") {
+            for section in self.object_list(item, offset)? {
+                let object = self.object(section, true)?;
+                if self.boolean(section, "light")?.unwrap_or(false) {
+                    if !self.samples_as_light(section)? {
+                        let message = "Only spheres, quads and boxes, or transforms of them, can be lights".to_string();
+                        return Err(self.error(section.get("light").unwrap().1, message));
+                    }
+                    camera.lights.add(object.clone());
+                }
+                world.add(object);
+            }
+        }
+
+        Ok(Scene {
+            camera,
+            world,
+            textures: std::mem::take(&mut self.textures),
+            materials: std::mem::take(&mut self.materials),
+            primitive_count: self.primitive_count,
+        })
+    }
+
+    fn camera(&self, section: Section, cam: &mut Camera) -> Result<(), SceneError> {
+        self.check_keys(
+            section,
+            "camera",
+            &[
+                "aspect_ratio", "image_width", "samples_per_pixel", "max_depth", "vfov", "lookfrom", "lookat", "vup",
+                "defocus_angle", "focus_dist", "background", "seed", "sampler", "adaptive_sampling",
+                "adaptive_threshold", "min_samples_per_pixel", "samples_per_pass", "russian_roulette_depth",
+                "integrator", "ao_distance", "shutter_open", "shutter_close", "shutter_curve", "projection",
+                "ortho_height", "fisheye_fov", "fisheye_mapping", "exposure", "focus_pixel", "focal_length", "sensor",
+                "f_number", "iso", "shutter_time", "units_per_meter",
+            ],
+        )?;
+
+        let numbers: [(&str, &mut f64); 10] = [
+            ("aspect_ratio", &mut cam.aspect_ratio),
+            ("vfov", &mut cam.vfov),
+            ("ortho_height", &mut cam.ortho_height),
+            ("fisheye_fov", &mut cam.fisheye_fov),
+            ("defocus_angle", &mut cam.defocus_angle),
+            ("focus_dist", &mut cam.focus_dist),
+            ("exposure", &mut cam.exposure),
+            ("adaptive_threshold", &mut cam.adaptive_threshold),
+            ("shutter_open", &mut cam.shutter_open),
+            ("shutter_close", &mut cam.shutter_close),
+        ];
+        for (key, field) in numbers {
+            if let Some(value) = self.number(section, key)? {
+                *field = value;
+            }
+        }
+
+        let integers: [(&str, &mut i32); 6] = [
+            ("image_width", &mut cam.image_width),
+            ("samples_per_pixel", &mut cam.samples_per_pixel),
+            ("max_depth", &mut cam.max_depth),
+            ("min_samples_per_pixel", &mut cam.min_samples_per_pixel),
+            ("samples_per_pass", &mut cam.samples_per_pass),
+            ("russian_roulette_depth", &mut cam.russian_roulette_depth),
+        ];
+        for (key, field) in integers {
+            if let Some(value) = self.integer(section, key)? {
+                let offset = section.get(key).unwrap().1;
+                *field = i32::try_from(value).map_err(|_| self.error(offset, format!("'{key}' is out of range")))?;
+            }
+        }
+
+        if cam.shutter_close < cam.shutter_open {
+            let offset = section.get("shutter_close").or(section.get("shutter_open")).unwrap().1;
+            return Err(self.error(offset, "'shutter_close' can't be before 'shutter_open'".to_string()));
+        }
+
+        // Sizes the renderer divides by or allocates with
+        let sizes = ["aspect_ratio", "image_width", "samples_per_pixel", "max_depth", "vfov", "focus_dist", "ortho_height"];
+        for key in sizes {
+            if let Some((item, offset)) = section.get(key) {
+                if as_number(item).is_some_and(|value| !value.is_finite() || value <= 0.0) {
+                    return Err(self.error(offset, format!("'{key}' must be positive")));
+                }
+            }
+        }
+        // Past a full turn the fisheye circle would fold over itself
+        if let Some((_, offset)) = section.get("fisheye_fov") {
+            if !(cam.fisheye_fov > 0.0 && cam.fisheye_fov <= 360.0) {
+                return Err(self.error(offset, "'fisheye_fov' must be above 0 and at most 360".to_string()));
+            }
+        }
+
+        let vectors: [(&str, &mut Vec3); 4] = [
+            ("lookfrom", &mut cam.lookfrom),
+            ("lookat", &mut cam.lookat),
+            ("vup", &mut cam.vup),
+            ("background", &mut cam.background),
+        ];
+        for (key, field) in vectors {
+            if let Some(value) = self.vector(section, key)? {
+                *field = value;
+            }
+        }
+
+        self.physical_camera(section, cam)?;
+
+        if let Some((item, offset)) = section.get("focus_pixel") {
+            let pixel: Option<Vec<i64>> = item.as_array().map(|a| a.iter().map(|v| v.as_integer()).collect()).unwrap_or(None);
+            match pixel.as_deref() {
+                Some(&[x, y]) => {
+                    // The size the camera will give the image, see Camera::initialize
+                    let height = ((cam.image_width as f64 / cam.aspect_ratio) as i32).max(1);
+                    if !(0..cam.image_width as i64).contains(&x) || !(0..height as i64).contains(&y) {
+                        let message = format!("'focus_pixel' ({x}, {y}) is outside the {}x{height} image", cam.image_width);
+                        return Err(self.error(offset, message));
+                    }
+                    cam.focus_pixel = Some((x as i32, y as i32));
+                }
+                _ => return Err(self.error(offset, "'focus_pixel' must be an array of 2 integers".to_string())),
+            }
+        }
+
+        if let Some(seed) = self.integer(section, "seed")? {
+            cam.seed = seed as u64;
+        }
+        if let Some(adaptive) = self.boolean(section, "adaptive_sampling")? {
+            cam.adaptive_sampling = adaptive;
+        }
+
+        if let Some(name) = self.string(section, "sampler")? {
+            cam.sampler = match name {
+                "independent" => SamplerType::Independent,
+                "stratified" => SamplerType::Stratified,
+                "halton" => SamplerType::Halton,
+                "sobol" => SamplerType::Sobol,
+                _ => return Err(self.error(section.get("sampler").unwrap().1, format!("Unknown sampler '{name}'"))),
+            };
+        }
+
+        let mapping = match self.string(section, "fisheye_mapping")? {
+            None | Some("equidistant") => FisheyeMapping::Equidistant,
+            Some("equisolid") => FisheyeMapping::Equisolid,
+            Some(name) => {
+                let offset = section.get("fisheye_mapping").unwrap().1;
+                return Err(self.error(offset, format!("Unknown fisheye mapping '{name}'")));
+            }
+        };
+        if let Some(name) = self.string(section, "projection")? {
+            cam.projection = match name {
+                "perspective" => Projection::Perspective,
+                "orthographic" => Projection::Orthographic,
+                "equirectangular" => Projection::Equirectangular,
+                "fisheye" => Projection::Fisheye(mapping),
+                _ => {
+                    let offset = section.get("projection").unwrap().1;
+                    return Err(self.error(offset, format!("Unknown projection '{name}'")));
+                }
+            };
+        }
+
+        if let Some((item, offset)) = section.get("shutter_curve") {
+            cam.shutter_curve = match (item.as_str(), item.as_array()) {
+                (Some("box"), _) => ShutterCurve::Box,
+                (Some("triangle"), _) => ShutterCurve::Triangle,
+                (Some(name), _) => return Err(self.error(offset, format!("Unknown shutter curve '{name}'"))),
+                (None, Some(array)) => {
+                    let values: Option<Vec<f64>> =
+                        array.iter().map(|v| v.as_float().or(v.as_integer().map(|i| i as f64))).collect();
+                    let values = values.ok_or_else(|| self.error(offset, "Shutter curve values must be numbers".to_string()))?;
+                    ShutterCurve::custom(values).map_err(|e| self.error(offset, e))?
+                }
+                (None, None) => {
+                    let message = "'shutter_curve' must be box, triangle or an array of numbers".to_string();
+                    return Err(self.error(offset, message));
+                }
+            };
+        }
+
+        let ao_distance = self.number(section, "ao_distance")?.unwrap_or(1.0);
+        if let Some(name) = self.string(section, "integrator")? {
+            cam.integrator = match name {
+                "path" => Arc::new(PathIntegrator) as Arc<dyn Integrator>,
+                "direct" => Arc::new(DirectLightingIntegrator),
+                "ao" => Arc::new(AmbientOcclusionIntegrator::new(ao_distance)),
+                "normal" => Arc::new(NormalIntegrator),
+                "uv" => Arc::new(UvIntegrator),
+                "albedo" => Arc::new(AlbedoIntegrator),
+                _ => {
+                    let offset = section.get("integrator").unwrap().1;
+                    return Err(self.error(offset, format!("Unknown integrator '{name}'")));
+                }
+            };
+        }
+
+        Ok(())
+    }
+
+    // Photographic settings, used when focal_length is given. They replace vfov, defocus_angle
+    // and exposure, and set the aspect ratio unless it is given too
+    fn physical_camera(&self, section: Section, cam: &mut Camera) -> Result<(), SceneError> {
+        const KEYS: [&str; 5] = ["sensor", "f_number", "iso", "shutter_time", "units_per_meter"];
+        let Some(focal_length) = self.number(section, "focal_length")? else {
+            return match KEYS.iter().find_map(|key| section.get(key)) {
+                Some((_, offset)) => Err(self.error(offset, "Physical camera settings need a 'focal_length'".to_string())),
+                None => Ok(()),
+            };
+        };
+        for key in ["vfov", "defocus_angle", "exposure"] {
+            if let Some((_, offset)) = section.get(key) {
+                return Err(self.error(offset, format!("'{key}' can't be combined with 'focal_length'")));
+            }
+        }
+
+        let mut settings = PhysicalCamera::new();
+        settings.focal_length = focal_length;
+        settings.focus_dist = cam.focus_dist;
+        let numbers: [(&str, &mut f64); 4] = [
+            ("f_number", &mut settings.f_number),
+            ("iso", &mut settings.iso),
+            ("shutter_time", &mut settings.shutter_time),
+            ("units_per_meter", &mut settings.units_per_meter),
+        ];
+        for (key, field) in numbers {
+            if let Some(value) = self.number(section, key)? {
+                *field = value;
+            }
+        }
+
+        if let Some((item, offset)) = section.get("sensor") {
+            settings.sensor = match item.as_str() {
+                Some("full_frame") => SensorFormat::FullFrame,
+                Some("aps_c") => SensorFormat::ApsC,
+                Some("micro_four_thirds") => SensorFormat::MicroFourThirds,
+                Some(name) => return Err(self.error(offset, format!("Unknown sensor '{name}'"))),
+                None => {
+                    let size: Option<Vec<f64>> = item
+                        .as_array()
+                        .map(|a| a.iter().map(|v| v.as_float().or(v.as_integer().map(|i| i as f64))).collect())
+                        .unwrap_or(None);
+                    match size.as_deref() {
+                        Some(&[width, height]) => SensorFormat::Custom(width, height),
+                        _ => {
+                            let message = "'sensor' must be full_frame, aps_c, micro_four_thirds or [width, height]";
+                            return Err(self.error(offset, message.to_string()));
+                        }
+                    }
+                }
+            };
+        }
+
+        let aspect_ratio = cam.aspect_ratio;
+        settings.apply(cam).map_err(|e| self.error(section.get("focal_length").unwrap().1, e))?;
+        if section.get("aspect_ratio").is_some() {
+            cam.aspect_ratio = aspect_ratio;
+        }
+        Ok(())
+    }
+
+    fn environment(&self, section: Section) -> Result<EnvironmentLight, SceneError> {
+        self.check_keys(section, "environment", &["path", "intensity", "rotation"])?;
+
+        let file = self.string(section, "path")?;
+        let file = self.required(section, "path", file)?;
+        let mut environment = EnvironmentLight::from_file(&self.base_dir.join(file).to_string_lossy())
+            .map_err(|e| self.error(section.get("path").unwrap().1, format!("Cannot load environment map: {e}")))?;
+
+        if let Some(intensity) = self.number(section, "intensity")? {
+            environment.intensity = intensity;
+        }
+        if let Some(rotation) = self.number(section, "rotation")? {
+            environment.rotation = rotation;
+        }
+        Ok(environment)
+    }
+
+    // A texture name or a plain color, under the given key or "texture"
+    fn texture_or_color(&self, section: Section, color_key: &str) -> Result<Option<Arc<dyn Texture>>, SceneError> {
+        let color = self.vector(section, color_key)?;
+        let name = self.string(section, "texture")?;
+        match (color, name) {
+            (Some(_), Some(_)) => {
+                Err(self.error(section.offset, format!("Give either '{color_key}' or 'texture', not both")))
+            }
+            (Some(color), None) => Ok(Some(Arc::new(SolidColor::from_color(color)))),
+            (None, Some(name)) => self.named_texture(section, "texture", name).map(Some),
+            (None, None) => Ok(None),
+        }
+    }
+
+    fn named_texture(&self, section: Section, key: &str, name: &str) -> Result<Arc<dyn Texture>, SceneError> {
+        match self.textures.get(name) {
+            Some(texture) => Ok(texture.clone()),
+            None => Err(self.error(section.get(key).unwrap().1, format!("Unknown texture '{name}'"))),
+        }
+    }
+
+    fn texture(&mut self, section: Section) -> Result<Arc<dyn Texture>, SceneError> {
+        let texture: Arc<dyn Texture> = match self.kind(section)? {
+            "solid" => {
+                self.check_keys(section, "a solid texture", &["type", "color"])?;
+                Arc::new(SolidColor::from_color(self.required_vector(section, "color")?))
+            }
+            "checker" => {
+                self.check_keys(section, "a checker texture", &["type", "scale", "even", "odd"])?;
+                let scale = self.required_number(section, "scale")?;
+                let even = self.checker_side(section, "even")?;
+                let odd = self.checker_side(section, "odd")?;
+                Arc::new(CheckerTexture::new(scale, even, odd))
+            }
+            "image" => {
+                self.check_keys(section, "an image texture", &["type", "path"])?;
+                let file = self.string(section, "path")?;
+                let file = self.required(section, "path", file)?;
+                let image = ImageTexture::from_file(&self.base_dir.join(file).to_string_lossy())
+                    .map_err(|e| self.error(section.get("path").unwrap().1, format!("Cannot load texture: {e}")))?;
+                Arc::new(image)
+            }
+            "noise" => {
+                self.check_keys(section, "a noise texture", &["type", "scale"])?;
+                let scale = self.required_number(section, "scale")?;
+                Arc::new(NoiseTexture::new(scale, &mut self.rng))
+            }
+            kind => return Err(self.error(section.get("type").unwrap().1, format!("Unknown texture type '{kind}'"))),
+        };
+        Ok(texture)
+    }
+
+    // Checker squares are either colors or other textures
+    fn checker_side(&self, section: Section, key: &str) -> Result<Arc<dyn Texture>, SceneError> {
+        if let Some((item, _)) = section.get(key) {
+            if let Some(name) = item.as_str() {
+                return self.named_texture(section, key, name);
+            }
+        }
+        Ok(Arc::new(SolidColor::from_color(self.required_vector(section, key)?)))
+    }
+
+    fn material(&self, section: Section) -> Result<Arc<dyn Material + Sync + Send>, SceneError> {
+        let missing = |key: &str| self.error(section.offset, format!("Missing '{key}' or 'texture'"));
+
+        let material: Arc<dyn Material + Sync + Send> = match self.kind(section)? {
+            "lambertian" => {
+                self.check_keys(section, "a lambertian material", &["type", "albedo", "texture"])?;
+                let texture = self.texture_or_color(section, "albedo")?.ok_or_else(|| missing("albedo"))?;
+                Arc::new(Lambertian::from_texture(texture))
+            }
+            "metal" => {
+                self.check_keys(section, "a metal material", &["type", "albedo", "fuzz"])?;
+                let albedo = self.required_vector(section, "albedo")?;
+                Arc::new(Metal::new(albedo, self.number(section, "fuzz")?.unwrap_or(0.0)))
+            }
+            "dielectric" => {
+                self.check_keys(section, "a dielectric material", &["type", "refraction_index"])?;
+                Arc::new(Dielectric::new(self.required_number(section, "refraction_index")?))
+            }
+            "diffuse_light" => {
+                self.check_keys(section, "a diffuse_light material", &["type", "emit", "texture"])?;
+                let texture = self.texture_or_color(section, "emit")?.ok_or_else(|| missing("emit"))?;
+                Arc::new(DiffuseLight::from_texture(texture))
+            }
+            "isotropic" => {
+                self.check_keys(section, "an isotropic material", &["type", "albedo", "texture"])?;
+                let texture = self.texture_or_color(section, "albedo")?.ok_or_else(|| missing("albedo"))?;
+                Arc::new(Isotropic::from_texture(texture))
+            }
+            kind => {
+                return Err(self.error(section.get("type").unwrap().1, format!("Unknown material type '{kind}'")))
+            }
+        };
+        Ok(material)
+    }
+
+    // Objects name a material or define their own
+    fn object_material(&self, section: Section) -> Result<Arc<dyn Material + Sync + Send>, SceneError> {
+        let (item, offset) = self.required(section, "material", section.get("material"))?;
+        if let Some(name) = item.as_str() {
+            return match self.materials.get(name) {
+                Some(material) => Ok(material.clone()),
+                None => Err(self.error(offset, format!("Unknown material '{name}'"))),
+            };
+        }
+        match item.as_table_like() {
+            Some(table) => self.material(Section { table, offset }),
+            None => Err(self.error(offset, "'material' must be a material name or table".to_string())),
+        }
+    }
+
+    // Either [[objects]] tables or an array of inline tables
+    fn object_list(&self, item: &'a Item, offset: usize) -> Result<Vec<Section<'a>>, SceneError> {
+        if let Some(tables) = item.as_array_of_tables() {
+            return Ok(tables
+                .iter()
+                .map(|table| Section { table, offset: table.span().map_or(offset, |s| s.start) })
+                .collect());
+        }
+
+        match item.as_array() {
+            Some(array) => array
+                .iter()
+                .map(|value| match value.as_inline_table() {
+                    Some(table) => Ok(Section { table, offset: value.span().map_or(offset, |s| s.start) }),
+                    None => Err(self.error(value.span().map_or(offset, |s| s.start), "Objects must be tables".to_string())),
+                })
+                .collect(),
+            None => Err(self.error(offset, "'objects' must be an array of tables".to_string())),
+        }
+    }
+
+    // Scale, rotation and translation of a transform or keyframe, all optional
+    fn placement(&self, section: Section, time: f64) -> Result<Keyframe, SceneError> {
+        let mut keyframe = Keyframe::new(time);
+        if let Some(factor) = section.get("scale").and_then(|(item, _)| as_number(item)) {
+            keyframe.scale = Vec3::new(factor, factor, factor);
+        } else if let Some(scale) = self.vector(section, "scale")? {
+            keyframe.scale = scale;
+        }
+
+        let axis = self.vector(section, "axis")?.unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+        if axis.near_zero() {
+            return Err(self.error(section.get("axis").unwrap().1, "'axis' must not be zero".to_string()));
+        }
+        keyframe.rotation = Quaternion::from_axis_angle(axis, self.number(section, "angle")?.unwrap_or(0.0));
+        keyframe.translation = self.vector(section, "translate")?.unwrap_or(Vec3::empty());
+        Ok(keyframe)
+    }
+
+    // The object held by a wrapper
+    fn child(&mut self, section: Section) -> Result<Arc<dyn Hittable>, SceneError> {
+        let (item, offset) = self.required(section, "object", section.get("object"))?;
+        self.object(self.table(item, offset, "'object'")?, false)
+    }
+
+    // Whether light sampling can aim at the object. Planes, media and bvhs give it no directions
+    fn samples_as_light(&self, section: Section<'a>) -> Result<bool, SceneError> {
+        match self.kind(section)? {
+            "sphere" | "quad" | "box" => Ok(true),
+            "translate" | "rotate_y" | "transform" | "animated_transform" => {
+                let (item, offset) = self.required(section, "object", section.get("object"))?;
+                self.samples_as_light(self.table(item, offset, "'object'")?)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn object(&mut self, section: Section, top_level: bool) -> Result<Arc<dyn Hittable>, SceneError> {
+        // Only top level objects sit where they are seen, so only they can be sampled as lights
+        let keys = |keys: &[&'static str]| {
+            let mut keys = keys.to_vec();
+            keys.push("type");
+            if top_level {
+                keys.push("light");
+            }
+            keys
+        };
+
+        let kind = self.kind(section)?;
+        self.primitive_count += match kind {
+            "sphere" | "quad" | "plane" => 1,
+            "box" => 6,
+            _ => 0,
+        };
+        let object: Arc<dyn Hittable> = match kind {
+            "sphere" => {
+                self.check_keys(section, "a sphere", &keys(&["center", "center2", "time", "time2", "radius", "material"]))?;
+                let center = self.required_vector(section, "center")?;
+                let radius = self.required_number(section, "radius")?;
+                let material = self.object_material(section)?;
+                // Moving from center at time to center2 at time2
+                let times = Interval::new(
+                    self.number(section, "time")?.unwrap_or(0.0),
+                    self.number(section, "time2")?.unwrap_or(1.0),
+                );
+                match self.vector(section, "center2")? {
+                    Some(_) if times.max <= times.min => {
+                        let offset = section.get("time2").or(section.get("time")).unwrap().1;
+                        return Err(self.error(offset, "'time2' must be after 'time'".to_string()));
+                    }
+                    Some(center2) => Arc::new(Sphere::new_moving(center, center2, times, radius, material)),
+                    None => Arc::new(Sphere::new(center, radius, material)),
+                }
+            }
+            "quad" => {
+                self.check_keys(section, "a quad", &keys(&["q", "u", "v", "material"]))?;
+                Arc::new(Quadrilateral::new(
+                    self.required_vector(section, "q")?,
+                    self.required_vector(section, "u")?,
+                    self.required_vector(section, "v")?,
+                    self.object_material(section)?,
+                ))
+            }
+            "box" => {
+                self.check_keys(section, "a box", &keys(&["a", "b", "material"]))?;
+                create_box(
+                    self.required_vector(section, "a")?,
+                    self.required_vector(section, "b")?,
+                    self.object_material(section)?,
+                )
+            }
+            "plane" => {
+                self.check_keys(section, "a plane", &keys(&["normal", "point", "material"]))?;
+                Arc::new(Plane::new(
+                    self.required_vector(section, "normal")?,
+                    self.required_vector(section, "point")?,
+                    self.object_material(section)?,
+                ))
+            }
+            "constant_medium" => {
+                self.check_keys(section, "a constant_medium", &keys(&["density", "albedo", "texture", "object"]))?;
+                let density = self.required_number(section, "density")?;
+                let texture = self.texture_or_color(section, "albedo")?;
+                let texture = texture.ok_or_else(|| self.error(section.offset, "Missing 'albedo' or 'texture'".to_string()))?;
+                Arc::new(ConstantMedium::new_from_texture(self.child(section)?, density, texture))
+            }
+            "translate" => {
+                self.check_keys(section, "a translate", &keys(&["offset", "object"]))?;
+                Arc::new(Translate::new(self.child(section)?, self.required_vector(section, "offset")?))
+            }
+            "rotate_y" => {
+                self.check_keys(section, "a rotate_y", &keys(&["angle", "object"]))?;
+                Arc::new(RotateY::new(self.child(section)?, self.required_number(section, "angle")?))
+            }
+            "transform" => {
+                self.check_keys(section, "a transform", &keys(&["scale", "axis", "angle", "translate", "object"]))?;
+                let matrix = self.placement(section, 0.0)?.matrix();
+                let object = self.child(section)?;
+                Arc::new(Transform::new(object, matrix).map_err(|e| self.error(section.offset, e))?)
+            }
+            "animated_transform" => {
+                self.check_keys(section, "an animated_transform", &keys(&["keyframes", "object"]))?;
+                let (item, offset) = self.required(section, "keyframes", section.get("keyframes"))?;
+                let keyframes = self
+                    .object_list(item, offset)?
+                    .into_iter()
+                    .map(|keyframe| {
+                        self.check_keys(keyframe, "a keyframe", &["time", "scale", "axis", "angle", "translate"])?;
+                        self.placement(keyframe, self.required_number(keyframe, "time")?)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let object = self.child(section)?;
+                Arc::new(AnimatedTransform::new(object, keyframes).map_err(|e| self.error(offset, e))?)
+            }
+            "bvh" => {
+                self.check_keys(section, "a bvh", &keys(&["objects"]))?;
+                let (item, offset) = self.required(section, "objects", section.get("objects"))?;
+                let mut objects = self
+                    .object_list(item, offset)?
+                    .into_iter()
+                    .map(|child| self.object(child, false))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if objects.is_empty() {
+                    return Err(self.error(offset, "A bvh needs at least one object".to_string()));
+                }
+                Arc::new(BVH::new(&mut objects))
+            }
+            kind => return Err(self.error(section.get("type").unwrap().1, format!("Unknown object type '{kind}'"))),
+        };
+        Ok(object)
+    }
+}
+
+fn as_number(item: &Item) -> Option<f64> {
+    item.as_float().or(item.as_integer().map(|i| i as f64))
+}
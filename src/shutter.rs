@@ -0,0 +1,78 @@
+// How open the shutter is over its interval. Ray times are drawn with this density, so
+// moments the shutter is only half open add half as much to the motion blur
+
+#[derive(Clone, Debug, Default)]
+pub enum ShutterCurve {
+    // Fully open from open to close
+    #[default]
+    Box,
+    // Opens linearly until the middle of the interval, then closes the same way
+    Triangle,
+    // Measured efficiency, see ShutterCurve::custom
+    Custom(ShutterTable),
+}
+
+// Efficiency at evenly spaced times from open to close, linear in between
+#[derive(Clone, Debug)]
+pub struct ShutterTable {
+    values: Vec<f64>,
+    // Running total of the area under each segment, normalized to end at 1
+    cdf: Vec<f64>,
+}
+
+impl ShutterCurve {
+    // At least two values, none negative and not all zero
+    pub fn custom(values: Vec<f64>) -> Result<Self, String> {
+        if values.len() < 2 {
+            return Err("A shutter curve needs at least 2 values.".to_string());
+        }
+        if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+            return Err("Shutter curve values must be finite and not negative.".to_string());
+        }
+
+        let mut cdf = Vec::with_capacity(values.len());
+        cdf.push(0.0);
+        for pair in values.windows(2) {
+            cdf.push(cdf[cdf.len() - 1] + 0.5 * (pair[0] + pair[1]));
+        }
+        let total = cdf[cdf.len() - 1];
+        if total <= 0.0 {
+            return Err("A shutter curve can't be closed the whole time.".to_string());
+        }
+        cdf.iter_mut().for_each(|c| *c /= total);
+
+        Ok(ShutterCurve::Custom(ShutterTable { values, cdf }))
+    }
+
+    // Fraction of the way from open to close, for a uniform u in [0, 1)
+    pub fn sample(&self, u: f64) -> f64 {
+        match self {
+            ShutterCurve::Box => u,
+            ShutterCurve::Triangle => {
+                if u < 0.5 {
+                    f64::sqrt(0.5 * u)
+                } else {
+                    1.0 - f64::sqrt(0.5 * (1.0 - u))
+                }
+            }
+            ShutterCurve::Custom(table) => table.sample(u),
+        }
+    }
+}
+
+impl ShutterTable {
+    fn sample(&self, u: f64) -> f64 {
+        let segments = self.values.len() - 1;
+        let segment = self.cdf[1..].partition_point(|&c| c <= u).min(segments - 1);
+
+        // Where u falls within the segment's share, then the inverse of the linear
+        // density a (1 - x) + b x over it, in a form that stays finite when a = b
+        let width = self.cdf[segment + 1] - self.cdf[segment];
+        let v = if width > 0.0 { ((u - self.cdf[segment]) / width).clamp(0.0, 1.0) } else { 0.0 };
+        let (a, b) = (self.values[segment], self.values[segment + 1]);
+        let denominator = a + f64::sqrt(a * a + (b * b - a * a) * v);
+        let x = if denominator > 0.0 { (a + b) * v / denominator } else { 0.0 };
+
+        (segment as f64 + x) / segments as f64
+    }
+}
@@ -4,12 +4,16 @@ use crate::aabb::AABB;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::material::Material;
+use crate::onb::Onb;
 use crate::ray::Ray;
-use crate::utils::PI;
-use crate::vec3::{dot, Point3, Vec3};
+use crate::sampler::Sampler;
+use crate::utils::{INFINITY, PI};
+use crate::vec3::{dot, random_unit_sphere, Point3, Vec3};
 
 pub struct Sphere {
     center: Ray,
+    // Start and end of the motion
+    times: Interval,
     radius: f64,
     bbox: AABB,
     mat: Arc<dyn Material + Sync + Send>,
@@ -21,18 +25,31 @@ impl Sphere {
         let bbox = AABB::from_points(center - rvec, center + rvec);
         Sphere {
             center: Ray::new(center, Vec3::empty()),
+            times: Interval::new(0.0, 1.0),
             radius: f64::max(radius,0.0),
             bbox: bbox,
             mat: mat,
         }
     }
 
+    // Moves from center at time 0 to center_next at time 1
     pub fn new_dynamic(
         center: Point3,
         center_next: Point3,
         radius: f64,
         mat: Arc<dyn Material + Sync + Send>,
     ) -> Self {
+        Sphere::new_moving(center, center_next, Interval::new(0.0, 1.0), radius, mat)
+    }
+
+    // Moves from center at times.min to center_next at times.max, holding still before and after
+    pub fn new_moving(
+        center: Point3,
+        center_next: Point3,
+        times: Interval,
+        radius: f64,
+        mat: Arc<dyn Material + Sync + Send>,
+    ) -> Self {
 
         let center_bounds = Ray::new(center, center_next - center);
         let rvec = Vec3::new(radius, radius, radius);
@@ -41,12 +58,24 @@ impl Sphere {
 
         Sphere {
             center: center_bounds,
+            times,
             radius: radius,
             bbox: AABB::from_bboxes(&bbox1, &bbox2),
             mat: mat,
         }
     }
 
+    // Fraction of the way along the motion at time
+    fn motion(&self, time: f64) -> f64 {
+        if self.times.size() > 0.0 {
+            ((time - self.times.min) / self.times.size()).clamp(0.0, 1.0)
+        } else if time < self.times.min {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
     fn get_sphere_uv(p: &Point3, u: &mut f64, v: &mut f64) {
         let theta = f64::acos(-p.y());
         let phi = f64::atan2(-p.z(), p.x()) + PI;
@@ -58,7 +87,7 @@ impl Sphere {
 
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, ray_t: &mut Interval, rec: &mut HitRecord) -> bool {
-        let current_center = self.center.at(r.time());
+        let current_center = self.center.at(self.motion(r.time()));
         let oc = current_center - r.origin();
         let a = r.direction().length_squared();
         let h = dot(r.direction(), oc);
@@ -91,4 +120,39 @@ impl Hittable for Sphere {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit(&Ray::new_with_time(origin, direction, time), &mut Interval::new(0.001, INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center.at(time) - origin).length_squared();
+        if distance_squared <= self.radius * self.radius {
+            // From the inside every direction sees the sphere
+            return 1.0 / (4.0 * PI);
+        }
+
+        let cos_theta_max = f64::sqrt(1.0 - self.radius * self.radius / distance_squared);
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    // Uniform over the cone of directions the sphere covers
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let direction = self.center.at(time) - origin;
+        let distance_squared = direction.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return random_unit_sphere(sampler);
+        }
+
+        let (r1, r2) = sampler.get_2d();
+        let z = 1.0 + r2 * (f64::sqrt(1.0 - self.radius * self.radius / distance_squared) - 1.0);
+        let phi = 2.0 * PI * r1;
+        let x = f64::cos(phi) * f64::sqrt(1.0 - z * z);
+        let y = f64::sin(phi) * f64::sqrt(1.0 - z * z);
+
+        Onb::new(direction).transform(Vec3::new(x, y, z))
+    }
 }
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::mesh::MeshData;
+use crate::vec3::Point3;
+
+// STL import, ASCII and binary. STL stores every facet with its own copies of the
+// corners, so corners at the same position are merged into one shared vertex.
+// Facet normals are ignored, faces are flat shaded from their winding
+
+#[derive(Debug)]
+pub enum StlError {
+    // The file could not be read
+    Io { path: PathBuf, message: String },
+    // Malformed ASCII statement, with the 1-based line it is on
+    Parse { path: PathBuf, line: usize, message: String },
+    // Binary data shorter than its facet count says
+    Truncated { path: PathBuf, message: String },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StlError::Io { path, message } => write!(f, "{}: {message}", path.display()),
+            StlError::Parse { path, line, message } => write!(f, "{}:{line}: {message}", path.display()),
+            StlError::Truncated { path, message } => write!(f, "{}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for StlError {}
+
+pub fn load_stl<P: AsRef<Path>>(path: P) -> Result<MeshData, StlError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| StlError::Io { path: path.to_path_buf(), message: e.to_string() })?;
+    parse_stl(&bytes, path)
+}
+
+// Parses a whole STL file. path names the file in errors
+pub fn parse_stl(bytes: &[u8], path: &Path) -> Result<MeshData, StlError> {
+    // Binary files may also start with "solid", so the size decides
+    if bytes.len() >= 84 {
+        let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+        if bytes.len() == 84 + 50 * count || !bytes.starts_with(b"solid") {
+            return parse_binary(bytes, count, path);
+        }
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) if text.trim_start().starts_with("solid") => parse_ascii(text, path),
+        _ => Err(StlError::Truncated { path: path.to_path_buf(), message: "Too short for a binary STL".to_string() }),
+    }
+}
+
+// Builds the indexed mesh, sharing corners with bit-identical positions
+struct MeshBuilder {
+    data: MeshData,
+    vertices: HashMap<[u64; 3], usize>,
+}
+
+impl MeshBuilder {
+    fn new() -> Self {
+        MeshBuilder { data: MeshData::default(), vertices: HashMap::new() }
+    }
+
+    fn vertex(&mut self, p: Point3) -> usize {
+        let positions = &mut self.data.positions;
+        *self.vertices.entry([p.x().to_bits(), p.y().to_bits(), p.z().to_bits()]).or_insert_with(|| {
+            positions.push(p);
+            positions.len() - 1
+        })
+    }
+
+    fn facet(&mut self, corners: [Point3; 3]) {
+        let face = [self.vertex(corners[0]), self.vertex(corners[1]), self.vertex(corners[2])];
+        self.data.indices.push(face);
+    }
+}
+
+fn parse_binary(bytes: &[u8], count: usize, path: &Path) -> Result<MeshData, StlError> {
+    if bytes.len() < 84 + 50 * count {
+        return Err(StlError::Truncated {
+            path: path.to_path_buf(),
+            message: format!("Header says {count} facets, the data only holds {}", (bytes.len() - 84) / 50),
+        });
+    }
+
+    let float = |offset: usize| {
+        f32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]) as f64
+    };
+    let point = |offset: usize| Point3::new(float(offset), float(offset + 4), float(offset + 8));
+
+    // Each facet is a normal, three corners and a 16 bit attribute
+    let mut builder = MeshBuilder::new();
+    for facet in 0..count {
+        let offset = 84 + 50 * facet;
+        builder.facet([point(offset + 12), point(offset + 24), point(offset + 36)]);
+    }
+
+    Ok(builder.data)
+}
+
+fn parse_ascii(text: &str, path: &Path) -> Result<MeshData, StlError> {
+    let error = |line: usize, message: String| StlError::Parse { path: path.to_path_buf(), line, message };
+
+    let mut builder = MeshBuilder::new();
+    let mut corners: Vec<Point3> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let mut args = line.split_whitespace();
+        match args.next() {
+            Some("facet") => corners.clear(),
+            Some("vertex") => {
+                let values: Vec<f64> = args
+                    .map(|a| a.parse::<f64>().map_err(|_| error(number + 1, format!("Invalid number '{a}'"))))
+                    .collect::<Result<_, _>>()?;
+                if values.len() != 3 {
+                    return Err(error(number + 1, format!("'vertex' needs 3 values, found {}", values.len())));
+                }
+                corners.push(Point3::new(values[0], values[1], values[2]));
+            }
+            Some("endfacet") => {
+                if corners.len() != 3 {
+                    return Err(error(number + 1, format!("Facet has {} vertices instead of 3", corners.len())));
+                }
+                builder.facet([corners[0], corners[1], corners[2]]);
+            }
+            // solid, outer loop, endloop, endsolid and facet normals
+            _ => {}
+        }
+    }
+
+    Ok(builder.data)
+}
@@ -4,6 +4,7 @@ use crate::color::Color;
 use crate::image::RayonettaImage;
 use crate::interval::Interval;
 use crate::perlin::Perlin;
+use crate::utils::Rng;
 use crate::vec3::Point3;
 
 pub trait Texture: Sync + Send {
@@ -78,12 +79,15 @@ pub struct ImageTexture {
 
 impl ImageTexture {
     pub fn from_image(filename: &str) -> Self {
-        let image = match RayonettaImage::from_file(filename) {
-            Ok(im) => im,
+        match ImageTexture::from_file(filename) {
+            Ok(texture) => texture,
             _ => panic!("Error opening file"),
-        };
+        }
+    }
 
-        ImageTexture { image: image }
+    pub fn from_file(filename: &str) -> Result<Self, String> {
+        let image = RayonettaImage::from_file(filename)?;
+        Ok(ImageTexture { image })
     }
 }
 
@@ -103,14 +107,37 @@ impl Texture for ImageTexture {
     }
 }
 
+// Blends the vertex colors of a mesh's faces. Meshes built with it pass the face index in the
+// whole part of u, half the barycentric weight of the second corner in the fractional part of
+// u, and the weight of the third corner in v
+pub struct VertexColorTexture {
+    colors: Vec<[Color; 3]>,
+}
+
+impl VertexColorTexture {
+    // The colors of each face's corners, in face order
+    pub fn new(colors: Vec<[Color; 3]>) -> Self {
+        VertexColorTexture { colors }
+    }
+}
+
+impl Texture for VertexColorTexture {
+    fn value(&self, u: f64, v: f64, _p: Point3) -> Color {
+        let face = (u.floor() as usize).min(self.colors.len() - 1);
+        let (b1, b2) = (2.0 * (u - face as f64), v);
+        let [c0, c1, c2] = self.colors[face];
+        (1.0 - b1 - b2) * c0 + b1 * c1 + b2 * c2
+    }
+}
+
 pub struct NoiseTexture {
     noise: Perlin,
     scale: f64,
 }
 
 impl NoiseTexture {
-    pub fn new(scale: f64) -> Self {
-        NoiseTexture { noise: Perlin::new(), scale: scale }
+    pub fn new(scale: f64, rng: &mut Rng) -> Self {
+        NoiseTexture { noise: Perlin::new(rng), scale: scale }
     }
 }
 
@@ -1,10 +1,14 @@
 use std::sync::Arc;
 
 use crate::aabb::AABB;
-use crate::hittable::Hittable;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::matrix::Matrix4;
+use crate::quaternion::Quaternion;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::utils::{degrees_to_radians, INFINITY};
-use crate::vec3::{Point3, Vec3};
+use crate::vec3::{unit_vector, Point3, Vec3};
 
 pub struct Translate {
     object: Arc<dyn Hittable>,
@@ -34,6 +38,15 @@ impl Hittable for Translate {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
+
+    // Moving doesn't change directions, only where they start from
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        self.object.pdf_value(origin - self.offset, direction, time)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        self.object.random(origin - self.offset, time, sampler)
+    }
 }
 
 pub struct RotateY {
@@ -49,6 +62,9 @@ impl RotateY {
         let sin_theta = radians.sin();
         let cos_theta = radians.cos();
         let bbox = object.bounding_box();
+        if !bbox.is_bounded() {
+            return RotateY { object, sin_theta, cos_theta, bbox: AABB::UNIVERSE };
+        }
 
         let mut min = Point3::new(INFINITY, INFINITY, INFINITY);
         let mut max = Point3::new(-INFINITY, -INFINITY, -INFINITY);
@@ -74,6 +90,14 @@ impl RotateY {
 
         RotateY { object: object, sin_theta: sin_theta, cos_theta: cos_theta, bbox: AABB::from_points(min, max) }
     }
+
+    fn to_object(&self, v: Vec3) -> Vec3 {
+        Vec3::new(self.cos_theta * v.x() - self.sin_theta * v.z(), v.y(), self.sin_theta * v.x() + self.cos_theta * v.z())
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        Vec3::new(self.cos_theta * v.x() + self.sin_theta * v.z(), v.y(), -self.sin_theta * v.x() + self.cos_theta * v.z())
+    }
 }
 
 impl Hittable for RotateY {
@@ -113,4 +137,227 @@ impl Hittable for RotateY {
     fn bounding_box(&self) -> AABB {
         self.bbox
     }
-}
\ No newline at end of file
+
+    // A rotation keeps solid angles, so the density carries over as it is
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        self.object.pdf_value(self.to_object(origin), self.to_object(direction), time)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        self.to_world(self.object.random(self.to_object(origin), time, sampler))
+    }
+}
+
+// Instance of an object placed by an affine matrix, mapping the object's own space to the
+// world. Many transforms can share one object, like copies of a mesh
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    matrix: Matrix4,
+    inverse: Matrix4,
+    bbox: AABB,
+}
+
+impl Transform {
+    pub fn new(object: Arc<dyn Hittable>, matrix: Matrix4) -> Result<Self, String> {
+        let inverse = matrix.inverse().ok_or("Transform matrix is not invertible.")?;
+        let bbox = matrix.transform_box(&object.bounding_box());
+        Ok(Transform { object, matrix, inverse, bbox })
+    }
+
+    pub fn matrix(&self) -> Matrix4 {
+        self.matrix
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, ray_t: &mut Interval, rec: &mut HitRecord) -> bool {
+        hit_placed(self.object.as_ref(), &self.matrix, &self.inverse, r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        pdf_placed(self.object.as_ref(), &self.inverse, origin, direction, time)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        self.matrix.transform_vector(self.object.random(self.inverse.transform_point(origin), time, sampler))
+    }
+}
+
+// Hit of an object placed by matrix, whose inverse maps the world back into the object's space
+fn hit_placed(
+    object: &dyn Hittable,
+    matrix: &Matrix4,
+    inverse: &Matrix4,
+    r: &Ray,
+    ray_t: &mut Interval,
+    rec: &mut HitRecord,
+) -> bool {
+    // The direction is not renormalized, so hit distances along the ray carry over
+    let object_r =
+        Ray::new_with_time(inverse.transform_point(r.origin()), inverse.transform_vector(r.direction()), r.time());
+
+    if !object.hit(&object_r, ray_t, rec) {
+        return false;
+    }
+
+    // The normal keeps facing against the ray, so front_face still holds
+    rec.p = matrix.transform_point(rec.p);
+    rec.normal = unit_vector(inverse.transform_normal(rec.normal));
+    true
+}
+
+// The object's density over its own directions, times how much the transform
+// squeezes solid angle around the direction
+fn pdf_placed(object: &dyn Hittable, inverse: &Matrix4, origin: Point3, direction: Vec3, time: f64) -> f64 {
+    let object_direction = inverse.transform_vector(unit_vector(direction));
+    let pdf = object.pdf_value(inverse.transform_point(origin), object_direction, time);
+    if pdf == 0.0 {
+        return 0.0;
+    }
+
+    pdf * inverse.linear_determinant().abs() / object_direction.length().powi(3)
+}
+
+// Placement of an animated object at one point in time: scaled, then rotated, then moved
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    // Leaves the object where it is
+    pub fn new(time: f64) -> Self {
+        Keyframe { time, translation: Vec3::empty(), rotation: Quaternion::IDENTITY, scale: Vec3::new(1.0, 1.0, 1.0) }
+    }
+
+    pub fn matrix(&self) -> Matrix4 {
+        Matrix4::translate(self.translation) * self.rotation.to_matrix() * Matrix4::scale(self.scale)
+    }
+
+    // Undoes the three steps in reverse, no general inversion needed
+    pub fn inverse(&self) -> Matrix4 {
+        let s = self.scale;
+        Matrix4::scale(Vec3::new(1.0 / s.x(), 1.0 / s.y(), 1.0 / s.z()))
+            * self.rotation.to_matrix().transpose()
+            * Matrix4::translate(-self.translation)
+    }
+
+    // Placement at time, t of the way from self to next
+    fn interpolate(&self, next: &Keyframe, time: f64) -> Keyframe {
+        let t = (time - self.time) / (next.time - self.time);
+        Keyframe {
+            time,
+            translation: (1.0 - t) * self.translation + t * next.translation,
+            rotation: self.rotation.slerp(&next.rotation, t),
+            scale: (1.0 - t) * self.scale + t * next.scale,
+        }
+    }
+}
+
+// Object moving through keyframed placements, blurred over the shutter like the moving
+// spheres. Before the first and after the last keyframe it holds still
+pub struct AnimatedTransform {
+    object: Arc<dyn Hittable>,
+    keyframes: Vec<Keyframe>,
+    bbox: AABB,
+}
+
+impl AnimatedTransform {
+    // Samples per keyframe interval when bounding the motion
+    const BOUND_STEPS: usize = 32;
+
+    pub fn new(object: Arc<dyn Hittable>, mut keyframes: Vec<Keyframe>) -> Result<Self, String> {
+        if keyframes.is_empty() {
+            return Err("Animated transform needs at least one keyframe.".to_string());
+        }
+        if keyframes.iter().any(|k| k.scale.x() == 0.0 || k.scale.y() == 0.0 || k.scale.z() == 0.0) {
+            return Err("Keyframe scales must not be zero.".to_string());
+        }
+        if keyframes.iter().any(|k| !k.time.is_finite()) {
+            return Err("Keyframe times must be finite.".to_string());
+        }
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        if keyframes.windows(2).any(|pair| pair[0].time == pair[1].time) {
+            return Err("Keyframe times must differ.".to_string());
+        }
+
+        let bbox = AnimatedTransform::motion_bounds(&object.bounding_box(), &keyframes);
+        Ok(AnimatedTransform { object, keyframes, bbox })
+    }
+
+    pub fn at(&self, time: f64) -> Keyframe {
+        let keyframes = &self.keyframes;
+        let next = keyframes.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return keyframes[0];
+        }
+        if next == keyframes.len() {
+            return keyframes[next - 1];
+        }
+        keyframes[next - 1].interpolate(&keyframes[next], time)
+    }
+
+    // Boxes at evenly spaced times, grown by how far any corner can get from the nearest
+    // sampled time. Within a step the box turns by at most the step angle and its
+    // translation and scale change linearly
+    fn motion_bounds(bbox: &AABB, keyframes: &[Keyframe]) -> AABB {
+        if !bbox.is_bounded() {
+            return AABB::UNIVERSE;
+        }
+
+        let mut bounds = keyframes[0].matrix().transform_box(bbox);
+        let corner = Vec3::new(
+            bbox.x.min.abs().max(bbox.x.max.abs()),
+            bbox.y.min.abs().max(bbox.y.max.abs()),
+            bbox.z.min.abs().max(bbox.z.max.abs()),
+        );
+
+        for pair in keyframes.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            let steps = AnimatedTransform::BOUND_STEPS as f64;
+            let largest_scale = (0..3).map(|i| a.scale[i].abs().max(b.scale[i].abs())).fold(0.0, f64::max);
+
+            let translation_step = (b.translation - a.translation).length() / steps;
+            let rotation_step = a.rotation.angle_to(&b.rotation) / steps * largest_scale * corner.length();
+            let scale_step = ((b.scale - a.scale) * corner).length() / steps;
+            let padding = 0.5 * (translation_step + rotation_step + scale_step);
+
+            for step in 1..=AnimatedTransform::BOUND_STEPS {
+                let time = a.time + (b.time - a.time) * step as f64 / steps;
+                let placed = a.interpolate(b, time).matrix().transform_box(bbox);
+                let grown =
+                    AABB::new(placed.x.expand(2.0 * padding), placed.y.expand(2.0 * padding), placed.z.expand(2.0 * padding));
+                bounds = AABB::from_bboxes(&bounds, &grown);
+            }
+        }
+
+        bounds
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, r: &Ray, ray_t: &mut Interval, rec: &mut HitRecord) -> bool {
+        let keyframe = self.at(r.time());
+        hit_placed(self.object.as_ref(), &keyframe.matrix(), &keyframe.inverse(), r, ray_t, rec)
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, time: f64) -> f64 {
+        pdf_placed(self.object.as_ref(), &self.at(time).inverse(), origin, direction, time)
+    }
+
+    fn random(&self, origin: Point3, time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let keyframe = self.at(time);
+        keyframe.matrix().transform_vector(self.object.random(keyframe.inverse().transform_point(origin), time, sampler))
+    }
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use crate::aabb::AABB;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::utils::INFINITY;
+use crate::vec3::{cross, dot, unit_vector, Point3, Vec3};
+
+// Watertight ray/triangle test (Woop, Benthin and Wald 2013). Rays through a shared edge
+// or vertex always hit one of the triangles, so meshes have no cracks.
+// Returns the ray parameter and the barycentric weights of p0, p1 and p2
+pub fn intersect_triangle(r: &Ray, ray_t: &Interval, p0: Point3, p1: Point3, p2: Point3) -> Option<(f64, [f64; 3])> {
+    let dir = r.direction();
+
+    // Permute the axes so the ray travels mostly along z
+    let kz = if f64::abs(dir.x()) > f64::abs(dir.y()) {
+        if f64::abs(dir.x()) > f64::abs(dir.z()) { 0 } else { 2 }
+    } else if f64::abs(dir.y()) > f64::abs(dir.z()) {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if dir[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    // Shear so the ray becomes the +z axis
+    let sx = dir[kx] / dir[kz];
+    let sy = dir[ky] / dir[kz];
+    let sz = 1.0 / dir[kz];
+
+    let a = p0 - r.origin();
+    let b = p1 - r.origin();
+    let c = p2 - r.origin();
+
+    let ax = a[kx] - sx * a[kz];
+    let ay = a[ky] - sy * a[kz];
+    let bx = b[kx] - sx * b[kz];
+    let by = b[ky] - sy * b[kz];
+    let cx = c[kx] - sx * c[kz];
+    let cy = c[ky] - sy * c[kz];
+
+    // Scaled barycentric coordinates from 2D edge functions
+    let u = cx * by - cy * bx;
+    let v = ax * cy - ay * cx;
+    let w = bx * ay - by * ax;
+
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    let t_scaled = u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz];
+    let t = t_scaled / det;
+    if !ray_t.surrounds(t) {
+        return None;
+    }
+
+    Some((t, [u / det, v / det, w / det]))
+}
+
+// Single triangle. Texture coordinates are the barycentric weights of the second and third vertices
+pub struct Triangle {
+    p: [Point3; 3],
+    normal: Vec3,
+    area: f64,
+    bbox: AABB,
+    mat: Arc<dyn Material + Sync + Send>,
+}
+
+impl Triangle {
+    pub fn new(p0: Point3, p1: Point3, p2: Point3, mat: Arc<dyn Material + Sync + Send>) -> Self {
+        let n = cross(p1 - p0, p2 - p0);
+
+        Triangle {
+            p: [p0, p1, p2],
+            normal: unit_vector(n),
+            area: 0.5 * n.length(),
+            bbox: triangle_bbox(p0, p1, p2),
+            mat,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: &mut Interval, rec: &mut HitRecord) -> bool {
+        let (t, b) = match intersect_triangle(r, ray_t, self.p[0], self.p[1], self.p[2]) {
+            Some(hit) => hit,
+            None => return false,
+        };
+
+        rec.t = t;
+        rec.p = b[0] * self.p[0] + b[1] * self.p[1] + b[2] * self.p[2];
+        rec.set_face_normal(r, &self.normal);
+        rec.u = b[1];
+        rec.v = b[2];
+        rec.mat = self.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3, _time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit(&Ray::new(origin, direction), &mut Interval::new(0.001, INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = f64::abs(dot(direction, rec.normal) / direction.length());
+
+        distance_squared / (cosine * self.area)
+    }
+
+    // Uniform point on the triangle
+    fn random(&self, origin: Point3, _time: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+        let s = f64::sqrt(r1);
+        let p = (1.0 - s) * self.p[0] + (s * (1.0 - r2)) * self.p[1] + (s * r2) * self.p[2];
+        p - origin
+    }
+}
+
+pub fn triangle_bbox(p0: Point3, p1: Point3, p2: Point3) -> AABB {
+    let min = Point3::new(
+        p0.x().min(p1.x()).min(p2.x()),
+        p0.y().min(p1.y()).min(p2.y()),
+        p0.z().min(p1.z()).min(p2.z()),
+    );
+    let max = Point3::new(
+        p0.x().max(p1.x()).max(p2.x()),
+        p0.y().max(p1.y()).max(p2.y()),
+        p0.z().max(p1.z()).max(p2.z()),
+    );
+
+    AABB::from_points(min, max)
+}
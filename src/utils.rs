@@ -1,5 +1,4 @@
-use rand::{distributions::Uniform, prelude::Distribution};
-use lazy_static::lazy_static;
+use rand::{rngs::SmallRng, Rng as _, SeedableRng};
 
 pub const INFINITY: f64 = f64::INFINITY;
 pub const PI: f64 = 3.1415926535897932385;
@@ -9,19 +8,50 @@ pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
-lazy_static!{
-    static ref UNIFORM_DIST: Uniform<f64> = Uniform::new(0.0, 1.0);
+// Seedable random number generator. All randomness in the renderer goes
+// through one of these so a render can be reproduced from its seed
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: SmallRng,
 }
 
-pub fn random_uniform() -> f64 {
-    let mut rng = rand::thread_rng();
-    UNIFORM_DIST.sample(&mut rng)
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: SmallRng::seed_from_u64(seed) }
+    }
+
+    // Stream for a single pixel sample. It only depends on its arguments, so
+    // the result does not change with the number of threads or their schedule
+    pub fn for_sample(seed: u64, i: i32, j: i32, sample: i32) -> Self {
+        Rng::new(hash(&[seed, i as u64, j as u64, sample as u64]))
+    }
+
+    pub fn uniform(&mut self) -> f64 {
+        self.state.gen::<f64>()
+    }
+}
+
+// Mixes a list of values into a single well distributed 64 bit hash (splitmix64 finalizer)
+pub fn hash(values: &[u64]) -> u64 {
+    let mut h: u64 = 0x9E37_79B9_7F4A_7C15;
+    for &value in values {
+        h ^= value;
+        h = h.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        h ^= h >> 31;
+    }
+    h
 }
 
-pub fn random_interval(min: f64, max: f64) -> f64 {
-    min + (max - min) * random_uniform()
+pub fn random_uniform(rng: &mut Rng) -> f64 {
+    rng.uniform()
 }
 
-pub fn random_int(min: i32, max: i32) -> i32 {
-    random_interval(min as f64, max as f64) as i32
-}
\ No newline at end of file
+pub fn random_interval(min: f64, max: f64, rng: &mut Rng) -> f64 {
+    min + (max - min) * random_uniform(rng)
+}
+
+pub fn random_int(min: i32, max: i32, rng: &mut Rng) -> i32 {
+    random_interval(min as f64, max as f64, rng) as i32
+}
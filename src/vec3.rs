@@ -1,6 +1,7 @@
 use std::ops::{self, Index, IndexMut};
 
-use crate::utils::{random_interval, random_uniform, PI};
+use crate::sampler::Sampler;
+use crate::utils::{random_interval, random_uniform, Rng, PI};
 
 #[derive(Clone, Copy, Debug, )]
 pub struct Vec3 {
@@ -42,18 +43,22 @@ impl Vec3 {
         self.x * self.x + self.y * self.y + self.z * self.z
     }
 
-    pub fn random() -> Vec3 {
-        Vec3::new(random_uniform(), random_uniform(), random_uniform())
+    pub fn random(rng: &mut Rng) -> Vec3 {
+        Vec3::new(random_uniform(rng), random_uniform(rng), random_uniform(rng))
     }
 
-    pub fn random_range(min: f64, max: f64) -> Vec3 {
-        Vec3::new(random_interval(min, max), random_interval(min, max), random_interval(min, max))
+    pub fn random_range(min: f64, max: f64, rng: &mut Rng) -> Vec3 {
+        Vec3::new(random_interval(min, max, rng), random_interval(min, max, rng), random_interval(min, max, rng))
     }
 
     pub fn near_zero(&self) -> bool {
         let s = 1e-8;
         (f64::abs(self.x) < s) && (f64::abs(self.y) < s) && (f64::abs(self.z) < s)
     }
+
+    pub fn max_component(&self) -> f64 {
+        f64::max(self.x, f64::max(self.y, self.z))
+    }
 }
 
 // Default Vec3
@@ -161,9 +166,8 @@ pub fn unit_vector(u: Vec3) -> Vec3 {
 
 // Sampling randomly from a unit disk with Fibonacci sequences:
 // https://observablehq.com/@meetamit/fibonacci-lattices
-pub fn random_unit_disk() -> Vec3 {
-    let sample_x = random_uniform();
-    let sample_y = random_uniform();
+pub fn random_unit_disk(sampler: &mut dyn Sampler) -> Vec3 {
+    let (sample_x, sample_y) = sampler.get_2d();
 
     let theta = 2.0 * PI * sample_x;
     let r = f64::sqrt(sample_y);
@@ -173,9 +177,8 @@ pub fn random_unit_disk() -> Vec3 {
 
 // Sampling randomly from a sphere more efficiently with Fibonacci sampling
 // https://extremelearning.com.au/how-to-evenly-distribute-points-on-a-sphere-more-effectively-than-the-canonical-fibonacci-lattice/
-pub fn random_unit_sphere() -> Vec3 {
-    let sample_theta = random_uniform();
-    let sample_phi = random_uniform();
+pub fn random_unit_sphere(sampler: &mut dyn Sampler) -> Vec3 {
+    let (sample_theta, sample_phi) = sampler.get_2d();
     let theta = 2.0 * PI * sample_theta;
     let cos_theta = f64::cos(theta);
     let sin_theta = f64::sin(theta);
@@ -187,8 +190,20 @@ pub fn random_unit_sphere() -> Vec3 {
     Vec3::new(cos_theta * sin_phi, sin_theta * sin_phi, cos_phi)
 }
 
-pub fn random_unit_hemisphere(normal: Vec3) -> Vec3 {
-    let on_unit_sphere = random_unit_sphere();
+// Cosine weighted direction around the z axis
+pub fn random_cosine_direction(sampler: &mut dyn Sampler) -> Vec3 {
+    let (r1, r2) = sampler.get_2d();
+
+    let phi = 2.0 * PI * r1;
+    let x = f64::cos(phi) * f64::sqrt(r2);
+    let y = f64::sin(phi) * f64::sqrt(r2);
+    let z = f64::sqrt(1.0 - r2);
+
+    Vec3::new(x, y, z)
+}
+
+pub fn random_unit_hemisphere(normal: Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+    let on_unit_sphere = random_unit_sphere(sampler);
 
     match dot(on_unit_sphere, normal) > 0.0 {
         true => on_unit_sphere,
@@ -214,7 +229,8 @@ mod tests {
 
     #[test]
     fn test_unit() {
-        let v = Vec3::random_range(-10.0, 10.0);
+        let mut rng = Rng::new(7);
+        let v = Vec3::random_range(-10.0, 10.0, &mut rng);
         let u = unit_vector(v);
         assert!(f64::abs(u.length()-1.0) < 1e-8);
     }
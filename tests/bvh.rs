@@ -0,0 +1,203 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bvh::{BvhOptions, BvhTree, BVH};
+    use color::Color;
+    use hittable::{HitRecord, Hittable};
+    use hittable_list::HittableList;
+    use interval::Interval;
+    use material::Lambertian;
+    use plane::Plane;
+    use planar::create_box;
+    use ray::Ray;
+    use rayonetta::*;
+    use sphere::Sphere;
+    use transformations::{RotateY, Translate};
+    use utils::{random_interval, Rng, INFINITY};
+    use vec3::{unit_vector, Point3, Vec3};
+
+    // Spheres of mixed sizes, some overlapping, inside a 20 unit cube
+    fn random_spheres(count: usize, rng: &mut Rng) -> Vec<Arc<dyn Hittable>> {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        (0..count)
+            .map(|_| {
+                let center = Point3::random_range(-10.0, 10.0, rng);
+                let radius = random_interval(0.05, 1.5, rng);
+                Arc::new(Sphere::new(center, radius, material.clone())) as Arc<dyn Hittable>
+            })
+            .collect()
+    }
+
+    fn closest_hit(world: &dyn Hittable, r: &Ray) -> Option<(f64, Point3)> {
+        let mut rec = HitRecord::new();
+        world.hit(r, &mut Interval::new(0.001, INFINITY), &mut rec).then_some((rec.t, rec.p))
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = Rng::new(3);
+        let objects = random_spheres(500, &mut rng);
+
+        let mut list = HittableList::new();
+        for object in &objects {
+            list.add(object.clone());
+        }
+
+        for max_leaf_size in [1, 4, 16] {
+            let mut options = BvhOptions::new();
+            options.max_leaf_size = max_leaf_size;
+            let bvh = BVH::with_options(&mut objects.clone(), &options);
+
+            let mut rng = Rng::new(11);
+            for _ in 0..2000 {
+                let origin = Point3::random_range(-15.0, 15.0, &mut rng);
+                let direction = unit_vector(Vec3::random_range(-1.0, 1.0, &mut rng));
+                let r = Ray::new(origin, direction);
+
+                match (closest_hit(&bvh, &r), closest_hit(&list, &r)) {
+                    (Some((t, p)), Some((expected_t, expected_p))) => {
+                        assert!((t - expected_t).abs() < 1e-9);
+                        assert!((p - expected_p).length() < 1e-9);
+                    }
+                    (None, None) => {}
+                    (found, expected) => panic!("BVH found {found:?}, brute force {expected:?}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stats_describe_the_tree() {
+        let mut rng = Rng::new(5);
+        let mut objects = random_spheres(1000, &mut rng);
+
+        let mut options = BvhOptions::new();
+        options.max_leaf_size = 8;
+        let stats = BVH::with_options(&mut objects, &options).stats();
+
+        assert_eq!(stats.object_count, 1000);
+        assert_eq!(stats.node_count, 2 * stats.leaf_count - 1);
+        assert!(stats.largest_leaf <= 8);
+        assert!(stats.depth < 40, "depth {}", stats.depth);
+        assert!(stats.sah_cost > 1.0 && stats.sah_cost < 100.0, "cost {}", stats.sah_cost);
+
+        // One object is a single leaf, not two copies of it
+        let stats = BVH::new(&mut objects[..1]).stats();
+        assert_eq!((stats.node_count, stats.leaf_count, stats.object_count), (1, 1, 1));
+    }
+
+    #[test]
+    fn binned_sah_beats_a_middle_split() {
+        // A dense floor of boxes with a few large objects far above it, like the final scene
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut rng = Rng::new(7);
+        let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                let a = Point3::new(i as f64 * 100.0 - 1000.0, 0.0, j as f64 * 100.0 - 1000.0);
+                let b = a + Vec3::new(100.0, random_interval(1.0, 101.0, &mut rng), 100.0);
+                objects.push(create_box(a, b, material.clone()));
+            }
+        }
+        for k in 0..10 {
+            let center = Point3::new(k as f64 * 60.0, 400.0, 200.0);
+            objects.push(Arc::new(Sphere::new(center, 50.0, material.clone())));
+        }
+
+        let sah = BVH::new(&mut objects.clone()).stats();
+
+        // Two bins only offer the middle of the centroid bounds as a split
+        let mut options = BvhOptions::new();
+        options.bins = 2;
+        let coarse = BVH::with_options(&mut objects, &options).stats();
+
+        assert!(sah.sah_cost < coarse.sah_cost, "{} >= {}", sah.sah_cost, coarse.sah_cost);
+    }
+
+    #[test]
+    fn flattened_traversal_matches_the_tree() {
+        let mut rng = Rng::new(13);
+        let mut objects = random_spheres(300, &mut rng);
+        let tree = BvhTree::new(&mut objects.clone());
+        let bvh = BVH::new(&mut objects);
+        assert_eq!(bvh.stats().node_count, tree.stats().node_count);
+
+        for _ in 0..2000 {
+            let origin = Point3::random_range(-15.0, 15.0, &mut rng);
+            let direction = unit_vector(Vec3::random_range(-1.0, 1.0, &mut rng));
+            let r = Ray::new(origin, direction);
+            assert_eq!(closest_hit(&bvh, &r).map(|(t, _)| t), closest_hit(&tree, &r).map(|(t, _)| t));
+        }
+
+        let empty = BVH::new(&mut []);
+        assert!(closest_hit(&empty, &Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))).is_none());
+    }
+
+    #[test]
+    fn lopsided_scenes_stay_within_the_traversal_stack() {
+        // Spheres doubling in size, the SAH peels them off one at a time
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut objects: Vec<Arc<dyn Hittable>> = (0..300)
+            .map(|i| {
+                let radius = 2f64.powi(i);
+                Arc::new(Sphere::new(Point3::new(3.0 * radius, 0.0, 0.0), radius, material.clone())) as Arc<dyn Hittable>
+            })
+            .collect();
+
+        let mut options = BvhOptions::new();
+        options.max_leaf_size = 1;
+        let bvh = BVH::with_options(&mut objects, &options);
+        assert!(bvh.stats().depth <= 64, "depth {}", bvh.stats().depth);
+
+        // A ray along the row passes through all of them
+        let r = Ray::new(Point3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let (t, _) = closest_hit(&bvh, &r).unwrap();
+        assert!((t - (10.0 + 2.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn planes_are_kept_next_to_the_tree() {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut rng = Rng::new(17);
+        let mut objects = random_spheres(200, &mut rng);
+        objects.push(Arc::new(Plane::new(Vec3::new(0.0, 1.0, 0.0), Point3::new(0.0, -12.0, 0.0), material.clone())));
+
+        // A plane nested in a list and behind transforms is just as unbounded
+        let mut wall = HittableList::new();
+        wall.add(Arc::new(Plane::new(Vec3::new(1.0, 0.0, 0.0), Point3::new(12.0, 0.0, 0.0), material.clone())));
+        objects.push(Arc::new(Translate::new(Arc::new(RotateY::new(Arc::new(wall), 30.0)), Vec3::new(0.0, 0.0, 2.0))));
+
+        let mut list = HittableList::new();
+        for object in &objects {
+            list.add(object.clone());
+        }
+
+        let bvh = BVH::new(&mut objects.clone());
+        let tree = BvhTree::new(&mut objects);
+        assert_eq!((bvh.stats().object_count, bvh.stats().unbounded_count), (200, 2));
+        assert!(!bvh.bounding_box().is_bounded() && !list.bounding_box().is_bounded());
+
+        let mut planes_hit = 0;
+        for _ in 0..2000 {
+            let origin = Point3::random_range(-11.0, 11.0, &mut rng);
+            let direction = unit_vector(Vec3::random_range(-1.0, 1.0, &mut rng));
+            let r = Ray::new(origin, direction);
+
+            let expected = closest_hit(&list, &r).map(|(t, _)| t);
+            assert_eq!(closest_hit(&bvh, &r).map(|(t, _)| t), expected);
+            assert_eq!(closest_hit(&tree, &r).map(|(t, _)| t), expected);
+            if expected.is_some_and(|t| r.at(t).y() < -11.99 || r.at(t).x() > 11.0) {
+                planes_hit += 1;
+            }
+        }
+        assert!(planes_hit > 100, "{planes_hit} rays hit a plane");
+
+        // Planes alone still make a working BVH, and one that can be nested in another
+        let floor: Arc<dyn Hittable> = Arc::new(BVH::new(&mut objects[200..201]));
+        let nested = BVH::new(&mut [floor]);
+        let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!((closest_hit(&nested, &r).unwrap().0 - 12.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,85 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use camera::Camera;
+    use color::Color;
+    use constant_medium::ConstantMedium;
+    use framebuffer::Framebuffer;
+    use hittable_list::HittableList;
+    use material::{Dielectric, Lambertian, Metal};
+    use rayonetta::*;
+    use sampler::SamplerType;
+    use sphere::Sphere;
+    use texture::NoiseTexture;
+    use utils::Rng;
+    use vec3::{Point3, Vec3};
+
+    fn world() -> HittableList {
+        let mut rng = Rng::new(3);
+        let mut world = HittableList::new();
+
+        let noise = Arc::new(NoiseTexture::new(4.0, &mut rng));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            Arc::new(Lambertian::from_texture(noise)),
+        )));
+        world.add(Arc::new(Sphere::new(Point3::new(-1.0, 0.0, -1.0), 0.5, Arc::new(Dielectric::new(1.5)))));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(1.0, 0.0, -1.0),
+            0.5,
+            Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.3)),
+        )));
+        world.add(Arc::new(ConstantMedium::new_from_color(
+            Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, Arc::new(Dielectric::new(1.5)))),
+            2.0,
+            Color::new(0.9, 0.9, 0.9),
+        )));
+
+        world
+    }
+
+    fn render(seed: u64, threads: usize, sampler: SamplerType) -> Framebuffer {
+        let world = world();
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 16.0 / 9.0;
+        cam.image_width = 32;
+        cam.samples_per_pixel = 4;
+        cam.max_depth = 10;
+        cam.background = Color::new(0.7, 0.8, 1.0);
+        cam.lookfrom = Point3::new(0.0, 0.0, 1.0);
+        cam.lookat = Point3::new(0.0, 0.0, -1.0);
+        cam.vup = Vec3::new(0.0, 1.0, 0.0);
+        cam.defocus_angle = 0.5;
+        cam.seed = seed;
+        cam.sampler = sampler;
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| cam.render_to_buffer(&world))
+    }
+
+    fn same(a: &Framebuffer, b: &Framebuffer) -> bool {
+        a.pixels().iter().zip(b.pixels()).all(|(p, q)| {
+            p.x().to_bits() == q.x().to_bits() && p.y().to_bits() == q.y().to_bits() && p.z().to_bits() == q.z().to_bits()
+        })
+    }
+
+    #[test]
+    fn same_seed_is_bit_reproducible() {
+        for sampler in [SamplerType::Independent, SamplerType::Stratified, SamplerType::Halton, SamplerType::Sobol] {
+            let a = render(42, 1, sampler);
+            let b = render(42, 4, sampler);
+            assert!(same(&a, &b));
+        }
+    }
+
+    #[test]
+    fn different_seed_changes_noise() {
+        let a = render(1, 2, SamplerType::Sobol);
+        let b = render(2, 2, SamplerType::Sobol);
+        assert!(!same(&a, &b));
+    }
+}
@@ -0,0 +1,69 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use camera::Camera;
+    use color::{luminance, Color};
+    use environment::EnvironmentLight;
+    use framebuffer::Framebuffer;
+    use hittable_list::HittableList;
+    use material::Lambertian;
+    use output::save_image;
+    use rayonetta::*;
+    use sphere::Sphere;
+    use vec3::{Point3, Vec3};
+
+    #[test]
+    fn load_hdr_map() {
+        // Bright top half, dark bottom half
+        let mut map = Framebuffer::new(8, 4);
+        for j in 0..4 {
+            for i in 0..8 {
+                let value = if j < 2 { 6.0 } else { 0.25 };
+                map.set_pixel(i, j, Color::new(value, value, value));
+            }
+        }
+
+        let path = std::env::temp_dir().join("rayonetta_environment_test.hdr");
+        save_image(&map, &path).unwrap();
+        let mut env = EnvironmentLight::from_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        env.intensity = 2.0;
+        assert!((env.radiance(Vec3::new(0.0, 1.0, 0.0)).x() - 12.0).abs() < 0.1);
+        assert!((env.radiance(Vec3::new(0.3, -1.0, 0.2)).x() - 0.5).abs() < 0.01);
+        assert!(EnvironmentLight::from_file("missing.exr").is_err());
+    }
+
+    #[test]
+    fn uniform_environment_matches_background() {
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))),
+        )));
+
+        let sky = Color::new(0.6, 0.7, 1.0);
+        let render = |environment: bool| {
+            let mut cam = Camera::new();
+            cam.aspect_ratio = 1.0;
+            cam.image_width = 8;
+            cam.samples_per_pixel = 256;
+            cam.vfov = 60.0;
+            cam.background = sky;
+            if environment {
+                cam.background = Color::empty();
+                cam.environment = Some(Arc::new(EnvironmentLight::from_color(sky)));
+            }
+
+            let image = cam.render_to_buffer(&world);
+            image.pixels().iter().map(luminance).sum::<f64>() / image.pixels().len() as f64
+        };
+
+        let background = render(false);
+        let environment = render(true);
+        assert!((background - environment).abs() / background < 0.02);
+    }
+}
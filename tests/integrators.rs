@@ -0,0 +1,64 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use camera::Camera;
+    use color::Color;
+    use framebuffer::Framebuffer;
+    use hittable_list::HittableList;
+    use integrator::{
+        AlbedoIntegrator, AmbientOcclusionIntegrator, Integrator, NormalIntegrator, UvIntegrator,
+    };
+    use material::Lambertian;
+    use rayonetta::*;
+    use sphere::Sphere;
+    use vec3::Point3;
+
+    fn render(integrator: Arc<dyn Integrator>) -> Framebuffer {
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -2.0),
+            1.0,
+            Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.6))),
+        )));
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 1.0;
+        cam.image_width = 9;
+        cam.samples_per_pixel = 4;
+        cam.vfov = 60.0;
+        cam.background = Color::new(0.7, 0.8, 1.0);
+        cam.integrator = integrator;
+
+        cam.render_to_buffer(&world)
+    }
+
+    fn close(a: Color, b: Color) -> bool {
+        (a - b).length() < 0.05
+    }
+
+    #[test]
+    fn normals_face_the_camera() {
+        let image = render(Arc::new(NormalIntegrator));
+        assert!(close(image.pixel(4, 4), Color::new(0.5, 0.5, 1.0)));
+        assert!(close(image.pixel(0, 0), Color::empty()));
+    }
+
+    #[test]
+    fn albedo_shows_the_unlit_surface() {
+        let image = render(Arc::new(AlbedoIntegrator));
+        assert!(close(image.pixel(4, 4), Color::new(0.2, 0.4, 0.6)));
+        assert!(close(image.pixel(0, 0), Color::new(0.7, 0.8, 1.0)));
+    }
+
+    #[test]
+    fn uv_and_occlusion_stay_in_range() {
+        let uv = render(Arc::new(UvIntegrator));
+        assert!(uv.pixels().iter().all(|c| (0.0..=1.0).contains(&c.x()) && (0.0..=1.0).contains(&c.y())));
+
+        // Nothing blocks a lone convex sphere
+        let ao = render(Arc::new(AmbientOcclusionIntegrator::new(10.0)));
+        assert!(close(ao.pixel(4, 4), Color::new(1.0, 1.0, 1.0)));
+    }
+}
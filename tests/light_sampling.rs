@@ -0,0 +1,79 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use camera::Camera;
+    use color::{luminance, Color};
+    use framebuffer::Framebuffer;
+    use hittable::Hittable;
+    use hittable_list::HittableList;
+    use material::{DiffuseLight, Lambertian};
+    use planar::Quadrilateral;
+    use rayonetta::*;
+    use sphere::Sphere;
+    use vec3::{Point3, Vec3};
+
+    // Floor and a sphere lit by a small quad and a small spherical light
+    fn render(use_lights: bool, samples_per_pixel: i32) -> Framebuffer {
+        let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+        let emit = Arc::new(DiffuseLight::from_color(Color::new(15.0, 15.0, 15.0)));
+
+        let quad: Arc<dyn Hittable> = Arc::new(Quadrilateral::new(
+            Point3::new(-0.5, 3.0, -0.5),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            emit.clone(),
+        ));
+        let bulb: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::new(2.0, 2.0, 1.0), 0.3, emit));
+
+        let mut world = HittableList::new();
+        world.add(Arc::new(Quadrilateral::new(
+            Point3::new(-5.0, 0.0, -5.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 10.0),
+            white.clone(),
+        )));
+        world.add(Arc::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, white)));
+        world.add(quad.clone());
+        world.add(bulb.clone());
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 1.0;
+        cam.image_width = 12;
+        cam.samples_per_pixel = samples_per_pixel;
+        cam.max_depth = 4;
+        // Both lights are out of frame, only their light reaches the image
+        cam.vfov = 40.0;
+        cam.lookfrom = Point3::new(0.0, 1.5, 5.0);
+        cam.lookat = Point3::new(0.0, 0.5, 0.0);
+        cam.seed = 5;
+        if use_lights {
+            cam.lights.add(quad);
+            cam.lights.add(bulb);
+        }
+
+        cam.render_to_buffer(&world)
+    }
+
+    fn mean_luminance(image: &Framebuffer) -> f64 {
+        image.pixels().iter().map(luminance).sum::<f64>() / image.pixels().len() as f64
+    }
+
+    fn squared_error(a: &Framebuffer, b: &Framebuffer) -> f64 {
+        a.pixels().iter().zip(b.pixels()).map(|(p, q)| (*p - *q).length_squared()).sum::<f64>()
+    }
+
+    #[test]
+    fn light_sampling_is_unbiased_and_less_noisy() {
+        let reference = render(true, 1024);
+        let brute = render(false, 4096);
+
+        let relative = (mean_luminance(&reference) - mean_luminance(&brute)).abs() / mean_luminance(&reference);
+        assert!(relative < 0.03, "mean luminance differs by {relative}");
+
+        let with_lights = render(true, 16);
+        let without_lights = render(false, 16);
+        assert!(squared_error(&with_lights, &reference) < 0.5 * squared_error(&without_lights, &reference));
+    }
+}
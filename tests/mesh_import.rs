@@ -0,0 +1,171 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use color::Color;
+    use hittable::{HitRecord, Hittable};
+    use interval::Interval;
+    use material::ScatterRecord;
+    use mesh::TriangleMesh;
+    use ply::{parse_ply, PlyError};
+    use ray::Ray;
+    use rayonetta::*;
+    use sampler::{IndependentSampler, Sampler};
+    use stl::{parse_stl, StlError};
+    use utils::INFINITY;
+    use vec3::{Point3, Vec3};
+
+    // Unit square at z = 0 with a red, a green, a blue and a white corner
+    const PLY_ASCII: &str = "\
+ply
+format ascii 1.0
+comment made by hand
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+0 1
+";
+
+    // Same square, in binary with the given byte order
+    fn ply_binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian { "binary_big_endian" } else { "binary_little_endian" };
+        let header = format!(
+            "ply\nformat {format} 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+             element face 1\nproperty list uchar uint vertex_indices\nend_header\n"
+        );
+
+        let mut bytes = header.into_bytes();
+        let corners = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        for value in corners.iter().flatten() {
+            bytes.extend(if big_endian { value.to_be_bytes() } else { value.to_le_bytes() });
+        }
+        bytes.push(4);
+        for index in 0u32..4 {
+            bytes.extend(if big_endian { index.to_be_bytes() } else { index.to_le_bytes() });
+        }
+        bytes
+    }
+
+    fn hit(mesh: &TriangleMesh, x: f64, y: f64) -> HitRecord {
+        let r = Ray::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::new();
+        assert!(mesh.hit(&r, &mut Interval::new(0.001, INFINITY), &mut rec));
+        rec
+    }
+
+    #[test]
+    fn ply_ascii_with_vertex_colors() {
+        let data = parse_ply(PLY_ASCII.as_bytes(), Path::new("square.ply")).unwrap();
+        assert_eq!(data.positions.len(), 4);
+        assert_eq!(data.triangle_count(), 2);
+        assert!((data.colors[1] - Color::new(0.0, 1.0, 0.0)).length() < 1e-12);
+
+        let mesh = TriangleMesh::with_vertex_colors(data).unwrap();
+        let mut sampler = IndependentSampler::new(1);
+        sampler.start_pixel_sample(0, 0, 0);
+
+        // Right on the green corner, halfway between red and green, then the same on the second face
+        // between blue and white
+        let points = [
+            (1.0 - 1e-9, 1e-9, Color::new(0.0, 1.0, 0.0)),
+            (0.5, 1e-9, Color::new(0.5, 0.5, 0.0)),
+            (1e-9, 1.0 - 1e-9, Color::new(1.0, 1.0, 1.0)),
+            (0.5, 1.0 - 1e-9, Color::new(0.5, 0.5, 1.0)),
+        ];
+        for (x, y, expected) in points {
+            let rec = hit(&mesh, x, y);
+            let r = Ray::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+            let mut srec = ScatterRecord::new();
+            assert!(rec.mat.scatter(&r, &rec, &mut srec, &mut sampler));
+            assert!((srec.attenuation - expected).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn ply_binary_both_byte_orders() {
+        for big_endian in [false, true] {
+            let data = parse_ply(&ply_binary(big_endian), Path::new("square.ply")).unwrap();
+            assert_eq!(data.triangle_count(), 2);
+            assert!((data.positions[2] - Point3::new(1.0, 1.0, 0.0)).length() < 1e-12);
+            assert!(data.colors.is_empty());
+        }
+    }
+
+    #[test]
+    fn ply_errors() {
+        let truncated = ply_binary(false);
+        let result = parse_ply(&truncated[..truncated.len() - 2], Path::new("cut.ply"));
+        assert!(matches!(result, Err(PlyError::Data { .. })));
+
+        let result = parse_ply(b"ply\nformat ascii 1.0\nproperty float x\nend_header\n", Path::new("bad.ply"));
+        assert!(matches!(result, Err(PlyError::Header { line: 3, .. })));
+
+        let out_of_range = PLY_ASCII.replace("4 0 1 2 3", "3 0 1 9");
+        assert!(parse_ply(out_of_range.as_bytes(), Path::new("bad.ply")).is_err());
+    }
+
+    #[test]
+    fn stl_ascii_and_binary_share_vertices() {
+        let ascii = "\
+solid square
+facet normal 0 0 1
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 1 1 0
+  endloop
+endfacet
+facet normal 0 0 1
+  outer loop
+    vertex 0 0 0
+    vertex 1 1 0
+    vertex 0 1 0
+  endloop
+endfacet
+endsolid square
+";
+        let data = parse_stl(ascii.as_bytes(), Path::new("square.stl")).unwrap();
+        assert_eq!(data.triangle_count(), 2);
+        assert_eq!(data.positions.len(), 4);
+
+        // Binary header starting with "solid" like some exporters write
+        let mut binary = b"solid exported as binary".to_vec();
+        binary.resize(80, 0);
+        binary.extend(2u32.to_le_bytes());
+        for facet in [[[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]], [[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]] {
+            binary.extend([0u8; 12]);
+            for value in facet.iter().flatten() {
+                binary.extend(value.to_le_bytes());
+            }
+            binary.extend([0u8; 2]);
+        }
+        let data = parse_stl(&binary, Path::new("square.stl")).unwrap();
+        assert_eq!(data.triangle_count(), 2);
+        assert_eq!(data.positions.len(), 4);
+
+        let mesh = TriangleMesh::new(data, std::sync::Arc::new(material::Lambertian::new(Color::new(0.5, 0.5, 0.5)))).unwrap();
+        assert!((hit(&mesh, 0.25, 0.75).p - Point3::new(0.25, 0.75, 0.0)).length() < 1e-9);
+
+        binary.truncate(binary.len() - 10);
+        assert!(matches!(parse_stl(&binary, Path::new("cut.stl")), Err(StlError::Truncated { .. })));
+
+        let bad = ascii.replace("vertex 1 0 0", "vertex 1 zero 0");
+        assert!(matches!(parse_stl(bad.as_bytes(), Path::new("bad.stl")), Err(StlError::Parse { line: 5, .. })));
+    }
+}
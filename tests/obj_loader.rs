@@ -0,0 +1,197 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use color::Color;
+    use framebuffer::Framebuffer;
+    use hittable::{HitRecord, Hittable};
+    use interval::Interval;
+    use material::ScatterRecord;
+    use obj::{load_obj, parse_obj, ObjError};
+    use output::save_image;
+    use ray::Ray;
+    use rayonetta::*;
+    use sampler::{IndependentSampler, Sampler};
+    use utils::INFINITY;
+    use vec3::{Point3, Vec3};
+
+    const MTL: &str = "\
+# Materials for the test scene
+newmtl lamp
+Ke 4 4 4
+
+newmtl glass
+Ni 1.5
+d 0.2
+
+newmtl chrome
+Kd 0.1 0.1 0.1
+Ks 0.9 0.9 0.9
+Ns 500
+
+newmtl checker
+Kd 0.5 0.5 0.5
+map_Kd checker.png
+";
+
+    // A textured quad at z = -1 and three one triangle objects behind it
+    const OBJ: &str = "\
+mtllib scene.mtl
+v -1 -1 -1
+v 1 -1 -1
+v 1 1 -1
+v -1 1 -1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl checker
+f 1/1/1 2/2/1 3/3/1 4/4/1
+v -1 -1 -2
+v 1 -1 -2
+v 0 1 -2
+usemtl chrome
+f -3 -2 -1
+usemtl glass
+f 5 6 7
+usemtl lamp
+f 5//1 6//1 7//1
+";
+
+    fn scene_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut checker = Framebuffer::new(2, 2);
+        checker.set_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        checker.set_pixel(1, 1, Color::new(1.0, 0.0, 0.0));
+        save_image(&checker, dir.join("checker.png")).unwrap();
+        fs::write(dir.join("scene.mtl"), MTL).unwrap();
+        fs::write(dir.join("scene.obj"), OBJ).unwrap();
+
+        dir
+    }
+
+    fn hit(objects: &dyn Hittable, origin: Point3) -> HitRecord {
+        let r = Ray::new(origin, Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::new();
+        assert!(objects.hit(&r, &mut Interval::new(0.001, INFINITY), &mut rec));
+        rec
+    }
+
+    #[test]
+    fn load_meshes_and_materials() {
+        let dir = scene_dir("rayonetta_obj_scene");
+        let scene = load_obj(dir.join("scene.obj")).unwrap();
+
+        assert_eq!(scene.triangle_count, 5);
+        assert_eq!(scene.objects.list.len(), 4);
+        assert_eq!(scene.lights.list.len(), 1);
+
+        // The quad interpolates its uvs and normal, and picks up the texture
+        let rec = hit(&scene.objects, Point3::new(0.5, -0.5, 0.0));
+        assert!((rec.u - 0.75).abs() < 1e-9 && (rec.v - 0.25).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+
+        let mut sampler = IndependentSampler::new(1);
+        sampler.start_pixel_sample(0, 0, 0);
+        let r = Ray::new(Point3::new(0.5, -0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut srec = ScatterRecord::new();
+        assert!(rec.mat.scatter(&r, &rec, &mut srec, &mut sampler));
+        assert!(!srec.is_specular());
+        assert!((srec.attenuation - Color::new(1.0, 0.0, 0.0)).length() < 1e-6);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn map_mtl_onto_materials() {
+        let dir = scene_dir("rayonetta_obj_materials");
+        let mut sampler = IndependentSampler::new(1);
+        sampler.start_pixel_sample(0, 0, 0);
+
+        // Single triangle using the given material
+        let mut scatter = |material: &str| {
+            let source = format!("mtllib scene.mtl\nv -1 -1 -2\nv 1 -1 -2\nv 0 1 -2\nusemtl {material}\nf 1 2 3\n");
+            let scene = parse_obj(&source, &dir.join("triangle.obj")).unwrap();
+
+            let rec = hit(&scene.objects, Point3::new(0.0, 0.0, 0.0));
+            let r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+            let mut srec = ScatterRecord::new();
+            let scattered = rec.mat.scatter(&r, &rec, &mut srec, &mut sampler);
+            (scattered, srec, rec.mat.emitted(rec.u, rec.v, rec.p))
+        };
+
+        let (scattered, _, emitted) = scatter("lamp");
+        assert!(!scattered && (emitted - Color::new(4.0, 4.0, 4.0)).length() < 1e-9);
+
+        let (scattered, srec, _) = scatter("glass");
+        assert!(scattered && srec.is_specular());
+        assert!((srec.attenuation - Color::new(1.0, 1.0, 1.0)).length() < 1e-9);
+
+        let (_, srec, _) = scatter("chrome");
+        assert!(srec.is_specular());
+        assert!((srec.attenuation - Color::new(0.9, 0.9, 0.9)).length() < 1e-9);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn map_options_before_a_file_name_with_spaces() {
+        let dir = std::env::temp_dir().join("rayonetta_obj_map_options");
+        fs::create_dir_all(&dir).unwrap();
+        save_image(&Framebuffer::new(2, 2), dir.join("my texture.png")).unwrap();
+        let options = "-s 1 1 1 -o 0.5 -blendu on -mm 0 1";
+        let mtl = format!("newmtl plain\nmap_Kd my texture.png\nnewmtl scaled\nmap_Kd {options} my texture.png\n");
+        fs::write(dir.join("scene.mtl"), mtl).unwrap();
+        fs::write(dir.join("bad.mtl"), "newmtl bad\nmap_Kd -halo 2 my texture.png\n").unwrap();
+
+        let triangle = |library: &str, material: &str| {
+            let source = format!("mtllib {library}\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl {material}\nf 1 2 3\n");
+            parse_obj(&source, &dir.join("triangle.obj"))
+        };
+        assert!(triangle("scene.mtl", "plain").is_ok());
+        assert!(triangle("scene.mtl", "scaled").is_ok());
+        match triangle("bad.mtl", "bad") {
+            Err(ObjError::Parse { line, message, .. }) => {
+                assert_eq!(line, 2);
+                assert!(message.contains("Unknown map option '-halo'"), "{message}");
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn report_malformed_files() {
+        let path = Path::new("broken.obj");
+
+        match parse_obj("v 0 0 0\nv 1 0 zero\n", path) {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, 2),
+            _ => panic!("expected a parse error"),
+        }
+
+        match parse_obj("v 0 0 0\nv 1 0 0\n\nf 1 2 3\n", path) {
+            Err(ObjError::Parse { line, message, .. }) => {
+                assert_eq!(line, 4);
+                assert!(message.contains("out of range"));
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        let missing = parse_obj("mtllib nowhere.mtl\n", path);
+        assert!(matches!(missing, Err(ObjError::Io { .. })));
+
+        let dir = std::env::temp_dir().join("rayonetta_obj_broken");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("bad.mtl"), "newmtl bad\nmap_Kd missing.png\n").unwrap();
+        let texture = parse_obj("mtllib bad.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl bad\nf 1 2 3\n", &dir.join("bad.obj"));
+        assert!(matches!(texture, Err(ObjError::Texture { .. })));
+        assert!(texture.err().unwrap().to_string().contains("missing.png"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use color::Color;
+    use framebuffer::Framebuffer;
+    use output::{save_image, OutputFormat};
+    use rayonetta::*;
+
+    fn gradient() -> Framebuffer {
+        let mut image = Framebuffer::new(4, 2);
+        for j in 0..2 {
+            for i in 0..4 {
+                image.set_pixel(i, j, Color::new(i as f64, 0.5, 4.0 * j as f64));
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(OutputFormat::from_path("render.PNG").unwrap(), OutputFormat::Png);
+        assert_eq!(OutputFormat::from_path("render.jpeg").unwrap(), OutputFormat::Jpeg);
+        assert_eq!(OutputFormat::from_path("out/render.exr").unwrap(), OutputFormat::Exr);
+        assert!(OutputFormat::from_path("render.gif").is_err());
+        assert!(OutputFormat::from_path("render").is_err());
+    }
+
+    #[test]
+    fn float_formats_keep_radiance_unclamped() {
+        let image = gradient();
+        let dir = std::env::temp_dir();
+
+        for name in ["rayonetta_output_test.hdr", "rayonetta_output_test.exr"] {
+            let path = dir.join(name);
+            save_image(&image, &path).unwrap();
+
+            let loaded = ::image::open(&path).unwrap().to_rgb32f();
+            assert_eq!(loaded.dimensions(), (4, 2));
+
+            let pixel = loaded.get_pixel(3, 1).0;
+            assert!((pixel[0] - 3.0).abs() < 0.05);
+            assert!((pixel[2] - 4.0).abs() < 0.05);
+
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn png_is_gamma_encoded() {
+        let image = gradient();
+        let path = std::env::temp_dir().join("rayonetta_output_test.png");
+        save_image(&image, &path).unwrap();
+
+        let loaded = ::image::open(&path).unwrap().to_rgb8();
+        // sqrt(0.5) * 256
+        assert_eq!(loaded.get_pixel(0, 0).0[1], 181);
+        assert_eq!(loaded.get_pixel(3, 1).0, [255, 181, 255]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use camera::Camera;
+    use camera_model::{CameraFrame, CameraModel, PerspectiveCamera};
+    use color::Color;
+    use hittable_list::HittableList;
+    use material::Lambertian;
+    use physical_camera::{PhysicalCamera, SensorFormat};
+    use rayonetta::*;
+    use sphere::Sphere;
+    use vec3::{Point3, Vec3};
+
+    fn aperture_radius(cam: &Camera) -> f64 {
+        cam.focus_dist * (cam.defocus_angle / 2.0).to_radians().tan()
+    }
+
+    #[test]
+    fn photographic_settings_convert() {
+        let mut settings = PhysicalCamera::new();
+        let cam = Camera::from_physical(&settings).unwrap();
+        assert!((cam.vfov - 2.0 * f64::atan(12.0 / 50.0).to_degrees()).abs() < 1e-12);
+        assert!((cam.aspect_ratio - 1.5).abs() < 1e-12);
+        // The sunny 16 rule is the reference exposure
+        assert!((cam.exposure - 1.0).abs() < 1e-12);
+
+        // 50mm at f/2 is a 25mm wide aperture
+        settings.f_number = 2.0;
+        settings.focus_dist = 3.0;
+        let cam = Camera::from_physical(&settings).unwrap();
+        assert!((aperture_radius(&cam) - 0.0125).abs() < 1e-12);
+        assert_eq!(cam.focus_dist, 3.0);
+        assert!((cam.exposure - 64.0).abs() < 1e-9);
+
+        // In centimeters the same lens is a hundred times wider
+        settings.units_per_meter = 100.0;
+        let cam = Camera::from_physical(&settings).unwrap();
+        assert!((aperture_radius(&cam) - 1.25).abs() < 1e-9);
+
+        // Twice the focal length on half the sensor height is a quarter of the tangent
+        settings.focal_length = 100.0;
+        settings.sensor = SensorFormat::Custom(16.0, 12.0);
+        let cam = Camera::from_physical(&settings).unwrap();
+        assert!(((cam.vfov / 2.0).to_radians().tan() - 0.06).abs() < 1e-12);
+        assert!((cam.aspect_ratio - 4.0 / 3.0).abs() < 1e-12);
+
+        settings.f_number = 0.0;
+        assert!(Camera::from_physical(&settings).is_err());
+    }
+
+    // Unit sphere 5 units in front of the camera, with the focus 10 units out
+    fn focus_on(pixel: (i32, i32), model: Option<Arc<dyn CameraModel>>) -> Camera {
+        let mut world = HittableList::new();
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, material)));
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 1.0;
+        cam.image_width = 9;
+        cam.samples_per_pixel = 1;
+        cam.vfov = 60.0;
+        cam.focus_dist = 10.0;
+        cam.defocus_angle = 2.0;
+        cam.focus_pixel = Some(pixel);
+        cam.model = model;
+        cam.render_to_buffer(&world);
+        cam
+    }
+
+    #[test]
+    fn auto_focus_moves_the_focus_plane() {
+        let radius = 10.0 * f64::tan(1.0_f64.to_radians());
+
+        // The center pixel sees the front of the sphere
+        let cam = focus_on((4, 4), None);
+        assert!((cam.focus_dist - 4.0).abs() < 0.01, "focus at {}", cam.focus_dist);
+        assert!((aperture_radius(&cam) - radius).abs() < 1e-12);
+
+        // A corner sees nothing, so nothing changes
+        let cam = focus_on((0, 0), None);
+        assert_eq!(cam.focus_dist, 10.0);
+        assert_eq!(cam.defocus_angle, 2.0);
+
+        // Neither does a pixel outside the image, or a camera with its own model
+        let cam = focus_on((4, 9), None);
+        assert_eq!(cam.focus_dist, 10.0);
+        let frame = CameraFrame::look_at(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let cam = focus_on((4, 4), Some(Arc::new(PerspectiveCamera::new(frame, 60.0, 1.0, 10.0, 2.0))));
+        assert_eq!(cam.focus_dist, 10.0);
+        assert_eq!(cam.defocus_angle, 2.0);
+    }
+
+    #[test]
+    fn exposure_scales_the_image() {
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 1.0;
+        cam.image_width = 4;
+        cam.samples_per_pixel = 2;
+        cam.background = Color::new(0.25, 0.5, 0.125);
+        cam.exposure = 2.0;
+        let image = cam.render_to_buffer(&HittableList::new());
+
+        let pixel = image.pixel(1, 2);
+        assert_eq!((pixel.x(), pixel.y(), pixel.z()), (0.5, 1.0, 0.25));
+    }
+}
@@ -0,0 +1,219 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use aabb::AABB;
+    use camera::{Camera, FisheyeMapping, Projection};
+    use camera_model::{CameraFrame, CameraModel, CameraSample, PerspectiveCamera};
+    use color::Color;
+    use framebuffer::Framebuffer;
+    use hittable::{HitRecord, Hittable};
+    use hittable_list::HittableList;
+    use interval::Interval;
+    use material::DiffuseLight;
+    use ray::Ray;
+    use rayonetta::*;
+    use sphere::Sphere;
+    use vec3::{Point3, Vec3};
+
+    // Background that remembers every camera ray
+    struct RayRecorder {
+        rays: Mutex<Vec<(Point3, Vec3, f64)>>,
+    }
+
+    impl Hittable for RayRecorder {
+        fn hit(&self, r: &Ray, _ray_t: &mut Interval, _rec: &mut HitRecord) -> bool {
+            self.rays.lock().unwrap().push((r.origin(), r.direction(), r.time()));
+            false
+        }
+
+        fn bounding_box(&self) -> AABB {
+            AABB::UNIVERSE
+        }
+    }
+
+    // Camera at z = 5 looking down -z onto a viewport 4 tall and 8 wide
+    fn record(defocus_angle: f64) -> Vec<(Point3, Vec3, f64)> {
+        let recorder = Arc::new(RayRecorder { rays: Mutex::new(Vec::new()) });
+        let world = HittableList::from_object(recorder.clone());
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 2.0;
+        cam.image_width = 32;
+        cam.samples_per_pixel = 4;
+        cam.projection = Projection::Orthographic;
+        cam.ortho_height = 4.0;
+        cam.lookfrom = Point3::new(0.0, 0.0, 5.0);
+        cam.lookat = Point3::new(0.0, 0.0, 0.0);
+        cam.focus_dist = 3.0;
+        cam.defocus_angle = defocus_angle;
+        cam.shutter_open = 1.0;
+        cam.shutter_close = 1.5;
+        cam.render_to_buffer(&world);
+
+        let rays = recorder.rays.lock().unwrap().clone();
+        assert_eq!(rays.len(), 32 * 16 * 4);
+        rays
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel() {
+        let rays = record(0.0);
+        for (origin, direction, time) in &rays {
+            assert!((origin.z() - 5.0).abs() < 1e-9);
+            assert!(direction.x().abs() < 1e-9 && direction.y().abs() < 1e-9 && direction.z() < 0.0);
+            assert!((1.0..1.5).contains(time));
+        }
+
+        // The origins cover the whole viewport rather than meeting at lookfrom
+        let extent = |axis: usize| {
+            let values = rays.iter().map(|(origin, _, _)| origin[axis]);
+            (values.clone().fold(f64::INFINITY, f64::min), values.fold(-f64::INFINITY, f64::max))
+        };
+        let (x, y) = (extent(0), extent(1));
+        assert!(x.0 > -4.0 && x.0 < -3.8 && x.1 < 4.0 && x.1 > 3.8, "{x:?}");
+        assert!(y.0 > -2.0 && y.0 < -1.8 && y.1 < 2.0 && y.1 > 1.8, "{y:?}");
+    }
+
+    #[test]
+    fn orthographic_defocus_stays_sharp_on_the_focus_plane() {
+        let rays = record(10.0);
+        assert!(rays.iter().any(|(_, direction, _)| direction.x().abs() > 1e-3));
+        for (origin, direction, _) in &rays {
+            // Every ray leaves the camera plane and lands on the focus plane 3 units ahead
+            assert!((origin.z() - 5.0).abs() < 1e-9);
+            assert!(((*origin + *direction).z() - 2.0).abs() < 1e-9);
+        }
+    }
+
+    // A small light 20 units from a camera at the origin looking down -z, against a background
+    fn render_light(projection: Projection, light: Option<Vec3>, background: Color, width: i32, height: i32) -> Framebuffer {
+        let mut world = HittableList::new();
+        if let Some(direction) = light {
+            let material = Arc::new(DiffuseLight::from_color(Color::new(1.0, 1.0, 1.0)));
+            world.add(Arc::new(Sphere::new(20.0 * direction, 1.4, material)));
+        }
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = width as f64 / height as f64;
+        cam.image_width = width;
+        cam.samples_per_pixel = 16;
+        cam.projection = projection;
+        cam.background = background;
+        cam.render_to_buffer(&world)
+    }
+
+    // Brightness weighted mean pixel position, in pixels from the top left corner
+    fn centroid(image: &Framebuffer) -> (f64, f64) {
+        let (mut x, mut y, mut total) = (0.0, 0.0, 0.0);
+        for j in 0..image.height() {
+            for i in 0..image.width() {
+                let value = image.pixel(i, j).x();
+                x += value * (i as f64 + 0.5);
+                y += value * (j as f64 + 0.5);
+                total += value;
+            }
+        }
+        assert!(total > 0.0, "the light is not in the image");
+        (x / total, y / total)
+    }
+
+    #[test]
+    fn equirectangular_covers_every_direction() {
+        let black = Color::empty();
+        let (x, y) = centroid(&render_light(Projection::Equirectangular, Some(Vec3::new(0.0, 0.0, -1.0)), black, 64, 32));
+        assert!((x - 32.0).abs() < 0.5 && (y - 16.0).abs() < 0.5, "ahead at ({x}, {y})");
+        let (x, y) = centroid(&render_light(Projection::Equirectangular, Some(Vec3::new(1.0, 0.0, 0.0)), black, 64, 32));
+        assert!((x - 48.0).abs() < 0.5 && (y - 16.0).abs() < 0.5, "right at ({x}, {y})");
+        let (x, y) = centroid(&render_light(Projection::Equirectangular, Some(Vec3::new(-1.0, 0.0, 0.0)), black, 64, 32));
+        assert!((x - 16.0).abs() < 0.5 && (y - 16.0).abs() < 0.5, "left at ({x}, {y})");
+        let (_, y) = centroid(&render_light(Projection::Equirectangular, Some(Vec3::new(0.0, 1.0, 0.0)), black, 64, 32));
+        assert!(y < 2.0, "up at {y}");
+
+        // Straight behind is split between the left and right edges
+        let image = render_light(Projection::Equirectangular, Some(Vec3::new(0.0, 0.0, 1.0)), black, 64, 32);
+        assert!(image.pixel(0, 16).x() > 0.0 && image.pixel(63, 16).x() > 0.0 && image.pixel(32, 16).x() == 0.0);
+    }
+
+    #[test]
+    fn fisheye_mappings_place_angles_by_radius() {
+        let black = Color::empty();
+        let white = Color::new(1.0, 1.0, 1.0);
+
+        // Only the inscribed circle sees anything
+        let image = render_light(Projection::Fisheye(FisheyeMapping::Equidistant), None, white, 64, 64);
+        assert_eq!(image.pixel(0, 0).x(), 0.0);
+        assert_eq!(image.pixel(63, 63).x(), 0.0);
+        assert!((image.pixel(32, 32).x() - 1.0).abs() < 1e-9);
+
+        // 60 degrees to the right of a 180 degree lens is 2/3 of the way to the edge when
+        // equidistant, and sin(30) / sin(45) of it when equisolid
+        let light = Some(Vec3::new(f64::sqrt(3.0) / 2.0, 0.0, -0.5));
+        let (x, y) = centroid(&render_light(Projection::Fisheye(FisheyeMapping::Equidistant), light, black, 64, 64));
+        assert!((x - (32.0 + 32.0 * 2.0 / 3.0)).abs() < 0.3 && (y - 32.0).abs() < 0.3, "equidistant at ({x}, {y})");
+        let (x, y) = centroid(&render_light(Projection::Fisheye(FisheyeMapping::Equisolid), light, black, 64, 64));
+        assert!((x - (32.0 + 32.0 * f64::sqrt(0.5))).abs() < 0.3 && (y - 32.0).abs() < 0.3, "equisolid at ({x}, {y})");
+    }
+
+    // Looks straight down -z from the right half of the film and records what it was given
+    struct HalfCamera {
+        samples: Mutex<Vec<CameraSample>>,
+    }
+
+    impl CameraModel for HalfCamera {
+        fn generate_ray(&self, sample: &CameraSample) -> Option<Ray> {
+            self.samples.lock().unwrap().push(*sample);
+            if sample.film.0 < 0.5 {
+                return None;
+            }
+            Some(Ray::new_with_time(Point3::empty(), Vec3::new(0.0, 0.0, -1.0), sample.time))
+        }
+    }
+
+    #[test]
+    fn models_plug_into_the_renderer() {
+        let model = Arc::new(HalfCamera { samples: Mutex::new(Vec::new()) });
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 2.0;
+        cam.image_width = 16;
+        cam.samples_per_pixel = 4;
+        cam.background = Color::new(1.0, 1.0, 1.0);
+        cam.shutter_open = 3.0;
+        cam.shutter_close = 4.0;
+        cam.model = Some(model.clone());
+        let image = cam.render_to_buffer(&HittableList::new());
+
+        for i in 0..16 {
+            let expected = if i < 8 { 0.0 } else { 1.0 };
+            assert_eq!(image.pixel(i, 4).x(), expected, "column {i}");
+        }
+
+        let samples = model.samples.lock().unwrap();
+        assert_eq!(samples.len(), 16 * 8 * 4);
+        for sample in samples.iter() {
+            assert!((0.0..=1.0).contains(&sample.film.0) && (0.0..=1.0).contains(&sample.film.1));
+            assert!(sample.lens.0 * sample.lens.0 + sample.lens.1 * sample.lens.1 <= 1.0);
+            assert!((3.0..4.0).contains(&sample.time));
+        }
+    }
+
+    #[test]
+    fn perspective_film_spans_the_field_of_view() {
+        let frame = CameraFrame::look_at(Point3::new(0.0, 0.0, 5.0), Point3::empty(), Vec3::new(0.0, 1.0, 0.0));
+        let model = PerspectiveCamera::new(frame, 90.0, 2.0, 4.0, 0.0);
+        let ray = |film: (f64, f64), lens: (f64, f64)| model.generate_ray(&CameraSample { film, lens, time: 0.5 }).unwrap();
+
+        // With a 90 degree field of view the top edge of the film is 45 degrees up
+        let center = ray((0.5, 0.5), (0.0, 0.0));
+        assert!((center.origin() - Point3::new(0.0, 0.0, 5.0)).length() < 1e-12);
+        assert!((center.direction() - Vec3::new(0.0, 0.0, -4.0)).length() < 1e-12);
+        assert!((ray((0.5, 0.0), (0.0, 0.0)).direction() - Vec3::new(0.0, 4.0, -4.0)).length() < 1e-12);
+        assert!((ray((1.0, 0.5), (0.0, 0.0)).direction() - Vec3::new(8.0, 0.0, -4.0)).length() < 1e-12);
+        assert_eq!(center.time(), 0.5);
+
+        // Without a defocus angle the lens sample is ignored
+        assert!((ray((0.5, 0.5), (1.0, 0.0)).origin() - center.origin()).length() < 1e-12);
+    }
+}
@@ -0,0 +1,105 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use camera::Camera;
+    use color::{luminance, Color};
+    use hittable_list::HittableList;
+    use material::{Dielectric, Lambertian};
+    use rayonetta::*;
+    use sphere::Sphere;
+    use vec3::Point3;
+
+    #[test]
+    fn render_empty_world_to_buffer() {
+        let world = HittableList::new();
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 2.0;
+        cam.image_width = 8;
+        cam.samples_per_pixel = 2;
+        cam.background = Color::new(0.25, 0.5, 1.0);
+
+        let image = cam.render_to_buffer(&world);
+
+        assert_eq!(image.width(), 8);
+        assert_eq!(image.height(), 4);
+        for pixel in image.pixels() {
+            assert!((*pixel - cam.background).length() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn framebuffer_ppm_header() {
+        let world = HittableList::new();
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 1.0;
+        cam.image_width = 2;
+        cam.samples_per_pixel = 1;
+        cam.background = Color::new(1.0, 1.0, 1.0);
+
+        let mut out = Vec::new();
+        cam.render_to_buffer(&world).write_ppm(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert!(text.starts_with("P3\n2 2\n255\n"));
+        assert_eq!(text.lines().filter(|l| *l == "255 255 255").count(), 4);
+    }
+
+    #[test]
+    fn adaptive_sampling_spends_samples_on_noise() {
+        let gray = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, gray.clone())));
+        world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, gray)));
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 1.0;
+        cam.image_width = 16;
+        cam.samples_per_pixel = 256;
+        cam.background = Color::new(1.0, 1.0, 1.0);
+        cam.adaptive_sampling = true;
+        cam.min_samples_per_pixel = 8;
+        cam.samples_per_pass = 8;
+        cam.adaptive_threshold = 0.02;
+
+        let image = cam.render_to_buffer(&world);
+
+        // The top corner only sees the flat background, the center sees the diffuse spheres
+        assert_eq!(image.sample_count(0, 0), 8);
+        assert!((image.pixel(0, 0) - cam.background).length() < 1e-8);
+        assert!(image.sample_count(8, 8) > 8);
+        assert!(image.sample_counts().iter().all(|&n| (8..=256).contains(&n)));
+
+        let heatmap = image.sample_heatmap();
+        assert_eq!((heatmap.width(), heatmap.height()), (16, 16));
+    }
+
+    #[test]
+    fn russian_roulette_keeps_the_mean() {
+        let gray = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, Arc::new(Dielectric::new(1.5)))));
+        world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, gray)));
+
+        let render = |russian_roulette_depth: i32| {
+            let mut cam = Camera::new();
+            cam.aspect_ratio = 1.0;
+            cam.image_width = 8;
+            cam.samples_per_pixel = 1024;
+            cam.max_depth = 10000;
+            cam.background = Color::new(1.0, 1.0, 1.0);
+            cam.russian_roulette_depth = russian_roulette_depth;
+
+            let image = cam.render_to_buffer(&world);
+            image.pixels().iter().map(luminance).sum::<f64>() / image.pixels().len() as f64
+        };
+
+        // Without roulette the deep paths are only ended by max_depth
+        let full = render(10000);
+        let roulette = render(1);
+        assert!((full - roulette).abs() / full < 0.02);
+    }
+}
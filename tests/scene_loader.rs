@@ -0,0 +1,349 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use camera::{FisheyeMapping, Projection};
+    use hittable::{HitRecord, Hittable};
+    use interval::Interval;
+    use ray::Ray;
+    use rayonetta::*;
+    use sampler::IndependentSampler;
+    use scene::{load_scene, parse_scene, SceneError};
+    use utils::INFINITY;
+    use vec3::{Point3, Vec3};
+
+    const SCENE: &str = r#"
+[camera]
+aspect_ratio = 2.0
+image_width = 64
+samples_per_pixel = 4
+lookfrom = [0, 0, 5]
+lookat = [0.0, 0.0, 0.0]
+integrator = "normal"
+sampler = "sobol"
+
+[textures.checker]
+type = "checker"
+scale = 0.5
+even = [0.0, 0.0, 0.0]
+odd = [1.0, 1.0, 1.0]
+
+[materials.floor]
+type = "lambertian"
+texture = "checker"
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = { type = "metal", albedo = [0.9, 0.9, 0.9], fuzz = 0.1 }
+
+[[objects]]
+type = "translate"
+offset = [0.0, 3.0, 0.0]
+object = { type = "box", a = [-1, -1, -1], b = [1, 1, 1], material = "floor" }
+
+[[objects]]
+type = "quad"
+q = [-1.0, 10.0, -1.0]
+u = [2.0, 0.0, 0.0]
+v = [0.0, 0.0, 2.0]
+material = { type = "diffuse_light", emit = [4.0, 4.0, 4.0] }
+light = true
+"#;
+
+    fn hit_t(world: &dyn Hittable, origin: Point3, direction: Vec3) -> Option<f64> {
+        let mut rec = HitRecord::new();
+        world.hit(&Ray::new(origin, direction), &mut Interval::new(0.001, INFINITY), &mut rec).then_some(rec.t)
+    }
+
+    fn parse_error(source: &str) -> (usize, String) {
+        match parse_scene(source, Path::new("test.toml")) {
+            Err(SceneError::Parse { line, message, .. }) => (line, message),
+            Err(e) => panic!("unexpected error {e}"),
+            Ok(_) => panic!("scene should not parse"),
+        }
+    }
+
+    #[test]
+    fn builds_camera_objects_and_lights() {
+        let scene = parse_scene(SCENE, Path::new("test.toml")).unwrap();
+
+        assert_eq!(scene.camera.aspect_ratio, 2.0);
+        assert_eq!(scene.camera.image_width, 64);
+        assert_eq!(scene.camera.samples_per_pixel, 4);
+        assert!((scene.camera.lookfrom - Point3::new(0.0, 0.0, 5.0)).length() < 1e-12);
+        assert_eq!(scene.world.list.len(), 3);
+        assert_eq!(scene.camera.lights.list.len(), 1);
+
+        // Sphere at the origin, box moved up to y = 3 and the light above both
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        assert!((hit_t(&scene.world, Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)).unwrap() - 4.0).abs() < 1e-9);
+        assert!((hit_t(&scene.world, Point3::new(0.0, 5.0, 0.0), down).unwrap() - 1.0).abs() < 1e-9);
+        assert!((hit_t(&scene.camera.lights, Point3::new(0.0, 20.0, 0.0), down).unwrap() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn moved_lights_are_sampled_and_unsampleable_ones_rejected() {
+        // The same 2x2 light 10 units above the origin, moved there and turned in place
+        let emit = "{ type = \"diffuse_light\", emit = [4, 4, 4] }";
+        let quad = format!("{{ type = \"quad\", q = [-1, 0, -1], u = [2, 0, 0], v = [0, 0, 2], material = {emit} }}");
+        let moved = format!("{{ type = \"translate\", offset = [0, 10, 0], object = {quad} }}");
+        let source = format!(
+            "[[objects]]\ntype = \"translate\"\noffset = [0, 10, 0]\nobject = {quad}\nlight = true\n\n\
+             [[objects]]\ntype = \"rotate_y\"\nangle = 90\nobject = {moved}\nlight = true\n"
+        );
+        let scene = parse_scene(&source, Path::new("test.toml")).unwrap();
+        assert_eq!(scene.camera.lights.list.len(), 2);
+
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let mut sampler = IndependentSampler::new(1);
+        for light in &scene.camera.lights.list {
+            // Distance squared over the area straight up
+            assert!((light.pdf_value(origin, Vec3::new(0.0, 1.0, 0.0), 0.0) - 25.0).abs() < 1e-9);
+            for _ in 0..20 {
+                let direction = light.random(origin, 0.0, &mut sampler);
+                assert!(light.pdf_value(origin, direction, 0.0) > 0.0);
+            }
+        }
+
+        let plane = "type = \"plane\"\nnormal = [0, 1, 0]\npoint = [0, 0, 0]";
+        let (line, message) = parse_error(&format!("[[objects]]\n{plane}\nmaterial = {emit}\nlight = true\n"));
+        assert_eq!(line, 6);
+        assert!(message.contains("can be lights"), "{message}");
+        let (line, message) = parse_error(&format!("[[objects]]\ntype = \"bvh\"\nobjects = [{quad}]\nlight = true\n"));
+        assert_eq!(line, 4);
+        assert!(message.contains("can be lights"), "{message}");
+        let medium = format!("{{ type = \"constant_medium\", density = 1, albedo = [1, 1, 1], object = {quad} }}");
+        let source = format!("[[objects]]\ntype = \"translate\"\noffset = [0, 1, 0]\nobject = {medium}\nlight = true\n");
+        let (line, message) = parse_error(&source);
+        assert_eq!(line, 5);
+        assert!(message.contains("can be lights"), "{message}");
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        // Line 1 is the empty line after r#"
+        let (line, message) = parse_error(&SCENE.replace("material = \"floor\"", "material = \"flor\""));
+        assert_eq!(line, 30);
+        assert!(message.contains("Unknown material 'flor'"), "{message}");
+
+        let (line, message) = parse_error(&SCENE.replace("radius = 1.0", "radius = \"big\""));
+        assert_eq!(line, 24);
+        assert!(message.contains("'radius' must be a number"), "{message}");
+
+        let (line, message) = parse_error(&SCENE.replace("radius = 1.0", "radus = 1.0"));
+        assert_eq!(line, 24);
+        assert!(message.contains("Unknown key 'radus'"), "{message}");
+
+        let (line, message) = parse_error(&SCENE.replace("radius = 1.0\n", ""));
+        assert_eq!(line, 21);
+        assert!(message.contains("Missing 'radius'"), "{message}");
+
+        let (line, _) = parse_error(&SCENE.replace("texture = \"checker\"", "texture = \"checker"));
+        assert_eq!(line, 19);
+
+        let (line, message) = parse_error(&SCENE.replace("type = \"quad\"", "type = \"disk\""));
+        assert_eq!(line, 33);
+        assert!(message.contains("Unknown object type 'disk'"), "{message}");
+    }
+
+    #[test]
+    fn camera_settings_are_validated() {
+        // Without a size the image is 400 wide at 16:9
+        let scene = parse_scene("[camera]\nimage_width = 8\n", Path::new("test.toml")).unwrap();
+        assert_eq!((scene.camera.image_width, scene.camera.aspect_ratio), (8, 16.0 / 9.0));
+        let scene = parse_scene("", Path::new("test.toml")).unwrap();
+        assert_eq!(scene.camera.image_width, 400);
+
+        for key in ["aspect_ratio", "image_width", "samples_per_pixel", "max_depth", "vfov", "focus_dist"] {
+            for value in ["0", "-2"] {
+                let (line, message) = parse_error(&format!("[camera]\nvup = [0, 1, 0]\n{key} = {value}\n"));
+                assert_eq!(line, 3, "{key}");
+                assert!(message.contains(&format!("'{key}' must be positive")), "{message}");
+            }
+        }
+
+        let (line, message) = parse_error("[camera]\nsamples_per_pixel = 4294967297\n");
+        assert_eq!(line, 2);
+        assert!(message.contains("'samples_per_pixel' is out of range"), "{message}");
+    }
+
+    #[test]
+    fn transforms_scale_rotate_and_move() {
+        let source = r#"
+[[objects]]
+type = "transform"
+scale = [2.0, 1.0, 1.0]
+axis = [0.0, 0.0, 1.0]
+angle = 90.0
+translate = [0.0, 0.0, -10.0]
+object = { type = "sphere", center = [0, 0, 0], radius = 1.0, material = { type = "lambertian", albedo = [0.5, 0.5, 0.5] } }
+"#;
+        let scene = parse_scene(source, Path::new("test.toml")).unwrap();
+
+        // The axis stretched along x ends up along y
+        let t = hit_t(&scene.world, Point3::new(0.0, 10.0, -10.0), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((t - 8.0).abs() < 1e-9);
+        let t = hit_t(&scene.world, Point3::new(10.0, 0.0, -10.0), Vec3::new(-1.0, 0.0, 0.0)).unwrap();
+        assert!((t - 9.0).abs() < 1e-9);
+
+        // Sliding down by 4 over the shutter
+        let animated = source.replace(
+            "scale = [2.0, 1.0, 1.0]",
+            "keyframes = [{ time = 0.0, scale = [2.0, 1.0, 1.0] }, { time = 1.0, translate = [0.0, -4.0, 0.0] }]",
+        );
+        let animated = animated.replace("type = \"transform\"", "type = \"animated_transform\"");
+        let animated = animated.replace("axis = [0.0, 0.0, 1.0]\nangle = 90.0\ntranslate = [0.0, 0.0, -10.0]\n", "");
+        let scene = parse_scene(&animated, Path::new("test.toml")).unwrap();
+        let mut rec = HitRecord::new();
+        let r = Ray::new_with_time(Point3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.5);
+        assert!(scene.world.hit(&r, &mut Interval::new(0.001, INFINITY), &mut rec));
+        assert!((rec.t - (10.0 - (-2.0 + 1.0))).abs() < 1e-9);
+
+        let (line, message) = parse_error(&source.replace("[0.0, 0.0, 1.0]", "[0.0, 0.0, 0.0]"));
+        assert_eq!(line, 5);
+        assert!(message.contains("'axis' must not be zero"), "{message}");
+
+        let (line, message) = parse_error(&source.replace("[2.0, 1.0, 1.0]", "[2.0, 0.0, 1.0]"));
+        assert_eq!(line, 2);
+        assert!(message.contains("not invertible"), "{message}");
+    }
+
+    #[test]
+    fn shutter_and_motion_times() {
+        let source = r#"
+[camera]
+shutter_open = 0.25
+shutter_close = 0.75
+shutter_curve = [0.0, 1.0, 0.0]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+center2 = [0.0, 2.0, 0.0]
+time = 0.5
+time2 = 1.5
+radius = 1.0
+material = { type = "lambertian", albedo = [0.5, 0.5, 0.5] }
+"#;
+        let scene = parse_scene(source, Path::new("test.toml")).unwrap();
+        assert_eq!((scene.camera.shutter_open, scene.camera.shutter_close), (0.25, 0.75));
+        assert!((scene.camera.shutter_curve.sample(0.5) - 0.5).abs() < 1e-12);
+        assert!((scene.camera.shutter_curve.sample(0.125) - 0.25).abs() < 1e-12);
+
+        let mut rec = HitRecord::new();
+        let r = Ray::new_with_time(Point3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 1.0);
+        assert!(scene.world.hit(&r, &mut Interval::new(0.001, INFINITY), &mut rec));
+        assert!((rec.t - 8.0).abs() < 1e-9);
+
+        let (line, message) = parse_error(&source.replace("[0.0, 1.0, 0.0]", "\"gaussian\""));
+        assert_eq!(line, 5);
+        assert!(message.contains("Unknown shutter curve 'gaussian'"), "{message}");
+        let (line, message) = parse_error(&source.replace("[0.0, 1.0, 0.0]", "[1.0, -1.0]"));
+        assert_eq!(line, 5);
+        assert!(message.contains("not negative"), "{message}");
+
+        let (line, message) = parse_error(&source.replace("shutter_close = 0.75", "shutter_close = 0.1"));
+        assert_eq!(line, 4);
+        assert!(message.contains("'shutter_close' can't be before 'shutter_open'"), "{message}");
+        let (line, message) = parse_error(&source.replace("time2 = 1.5", "time2 = 0.5"));
+        assert_eq!(line, 12);
+        assert!(message.contains("'time2' must be after 'time'"), "{message}");
+        let (line, message) = parse_error(&source.replace("time2 = 1.5\n", "").replace("time = 0.5", "time = 2.0"));
+        assert_eq!(line, 11);
+        assert!(message.contains("'time2' must be after 'time'"), "{message}");
+    }
+
+    #[test]
+    fn orthographic_projection() {
+        let source = "[camera]\nprojection = \"orthographic\"\northo_height = 6.5\n";
+        let scene = parse_scene(source, Path::new("test.toml")).unwrap();
+        assert_eq!(scene.camera.projection, Projection::Orthographic);
+        assert_eq!(scene.camera.ortho_height, 6.5);
+
+        let scene = parse_scene("[camera]\n", Path::new("test.toml")).unwrap();
+        assert_eq!(scene.camera.projection, Projection::Perspective);
+
+        let (line, message) = parse_error(&source.replace("orthographic", "isometric"));
+        assert_eq!(line, 2);
+        assert!(message.contains("Unknown projection 'isometric'"), "{message}");
+
+        // A flat or mirrored viewport
+        for height in ["0.0", "-6.5"] {
+            let (line, message) = parse_error(&source.replace("6.5", height));
+            assert_eq!(line, 3);
+            assert!(message.contains("'ortho_height' must be positive"), "{message}");
+        }
+    }
+
+    #[test]
+    fn fisheye_projection() {
+        let source = "[camera]\nprojection = \"fisheye\"\nfisheye_fov = 360\nfisheye_mapping = \"equisolid\"\n";
+        let scene = parse_scene(source, Path::new("test.toml")).unwrap();
+        assert_eq!(scene.camera.projection, Projection::Fisheye(FisheyeMapping::Equisolid));
+        assert_eq!(scene.camera.fisheye_fov, 360.0);
+
+        for fov in ["0", "-30.0", "360.5"] {
+            let (line, message) = parse_error(&source.replace("360", fov));
+            assert_eq!(line, 3);
+            assert!(message.contains("'fisheye_fov' must be above 0 and at most 360"), "{message}");
+        }
+    }
+
+    #[test]
+    fn physical_camera_settings() {
+        let source = "[camera]\nfocal_length = 35\nsensor = [24, 16]\nf_number = 4\nshutter_time = 0.01\nfocus_pixel = [3, 5]\n";
+        let scene = parse_scene(source, Path::new("test.toml")).unwrap();
+        let cam = &scene.camera;
+        assert!((cam.vfov - 2.0 * f64::atan(8.0 / 35.0).to_degrees()).abs() < 1e-12);
+        assert!((cam.aspect_ratio - 1.5).abs() < 1e-12);
+        assert!((cam.exposure - 16.0).abs() < 1e-9);
+        assert_eq!(cam.focus_pixel, Some((3, 5)));
+
+        // An explicit aspect ratio wins over the sensor's
+        let scene = parse_scene(&format!("{source}aspect_ratio = 2.0\n"), Path::new("test.toml")).unwrap();
+        assert_eq!(scene.camera.aspect_ratio, 2.0);
+
+        let (line, message) = parse_error(&format!("{source}vfov = 40\n"));
+        assert_eq!(line, 7);
+        assert!(message.contains("'vfov' can't be combined with 'focal_length'"), "{message}");
+        let (line, message) = parse_error(&source.replace("focal_length = 35", "vfov = 40"));
+        assert_eq!(line, 3);
+        assert!(message.contains("need a 'focal_length'"), "{message}");
+        let (line, message) = parse_error(&source.replace("[24, 16]", "\"medium_format\""));
+        assert_eq!(line, 3);
+        assert!(message.contains("Unknown sensor 'medium_format'"), "{message}");
+        let (line, message) = parse_error(&source.replace("f_number = 4", "f_number = 0"));
+        assert_eq!(line, 2);
+        assert!(message.contains("F-number must be positive"), "{message}");
+        let (line, message) = parse_error(&source.replace("[3, 5]", "[3]"));
+        assert_eq!(line, 6);
+        assert!(message.contains("'focus_pixel' must be an array of 2 integers"), "{message}");
+
+        // The image is 400x266 at the sensor's 3:2
+        for pixel in ["[400, 5]", "[3, 266]", "[-1, 5]", "[3, 5000000000]"] {
+            let (line, message) = parse_error(&source.replace("[3, 5]", pixel));
+            assert_eq!(line, 6);
+            assert!(message.contains("is outside the 400x266 image"), "{message}");
+        }
+        assert!(parse_scene(&source.replace("[3, 5]", "[399, 265]"), Path::new("test.toml")).is_ok());
+    }
+
+    #[test]
+    fn sample_scenes_load() {
+        let mut count = 0;
+        for entry in fs::read_dir("scenes").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "toml") {
+                if let Err(e) = load_scene(&path) {
+                    panic!("{e}");
+                }
+                count += 1;
+            }
+        }
+        assert!(count >= 9);
+    }
+}
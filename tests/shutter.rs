@@ -0,0 +1,141 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use aabb::AABB;
+    use camera::Camera;
+    use color::Color;
+    use hittable::{HitRecord, Hittable};
+    use hittable_list::HittableList;
+    use interval::Interval;
+    use material::Lambertian;
+    use ray::Ray;
+    use rayonetta::*;
+    use sampler::IndependentSampler;
+    use shutter::ShutterCurve;
+    use sphere::Sphere;
+    use vec3::{dot, unit_vector, Point3, Vec3};
+
+    // Fraction of 10000 evenly spread samples landing in each tenth of the interval
+    fn histogram(curve: &ShutterCurve) -> Vec<f64> {
+        let mut bins = vec![0.0; 10];
+        for i in 0..10000 {
+            let x = curve.sample((i as f64 + 0.5) / 10000.0);
+            assert!((0.0..=1.0).contains(&x));
+            bins[((x * 10.0) as usize).min(9)] += 1.0 / 10000.0;
+        }
+        bins
+    }
+
+    fn assert_histograms_match(a: &[f64], b: &[f64]) {
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() < 2e-3, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn curves_shape_the_time_density() {
+        assert_histograms_match(&histogram(&ShutterCurve::Box), &[0.1; 10]);
+
+        // The triangle is a tenth as likely near the ends as its peak in the middle
+        let triangle = histogram(&ShutterCurve::Triangle);
+        assert!((triangle[0] - 0.02).abs() < 1e-3 && (triangle[4] - 0.18).abs() < 1e-3, "{triangle:?}");
+
+        let flat = ShutterCurve::custom(vec![2.0, 2.0, 2.0]).unwrap();
+        assert_histograms_match(&histogram(&flat), &histogram(&ShutterCurve::Box));
+        let tent = ShutterCurve::custom(vec![0.0, 1.0, 0.0]).unwrap();
+        assert_histograms_match(&histogram(&tent), &triangle);
+
+        // A linear ramp samples the square root
+        let ramp = ShutterCurve::custom(vec![0.0, 1.0]).unwrap();
+        for u in [0.0, 0.1, 0.25, 0.5, 0.9] {
+            assert!((ramp.sample(u) - f64::sqrt(u)).abs() < 1e-12);
+        }
+
+        // Closed in the middle, nothing is exposed there
+        let gap = histogram(&ShutterCurve::custom(vec![1.0, 1.0, 0.0, 0.0, 1.0, 1.0]).unwrap());
+        assert!(gap[4] == 0.0 && gap[5] == 0.0, "{gap:?}");
+
+        assert!(ShutterCurve::custom(vec![1.0]).is_err());
+        assert!(ShutterCurve::custom(vec![1.0, -1.0]).is_err());
+        assert!(ShutterCurve::custom(vec![0.0, 0.0]).is_err());
+    }
+
+    // Background that remembers the time of every camera ray
+    struct TimeRecorder {
+        times: Mutex<Vec<f64>>,
+    }
+
+    impl Hittable for TimeRecorder {
+        fn hit(&self, r: &Ray, _ray_t: &mut Interval, _rec: &mut HitRecord) -> bool {
+            self.times.lock().unwrap().push(r.time());
+            false
+        }
+
+        fn bounding_box(&self) -> AABB {
+            AABB::UNIVERSE
+        }
+    }
+
+    #[test]
+    fn ray_times_fall_within_the_shutter() {
+        let recorder = Arc::new(TimeRecorder { times: Mutex::new(Vec::new()) });
+        let world = HittableList::from_object(recorder.clone());
+
+        let mut cam = Camera::new();
+        cam.aspect_ratio = 1.0;
+        cam.image_width = 16;
+        cam.samples_per_pixel = 16;
+        cam.shutter_open = 2.0;
+        cam.shutter_close = 2.5;
+        cam.render_to_buffer(&world);
+
+        let times = recorder.times.lock().unwrap();
+        assert_eq!(times.len(), 16 * 16 * 16);
+        assert!(times.iter().all(|t| (2.0..2.5).contains(t)));
+        let mean = times.iter().sum::<f64>() / times.len() as f64;
+        assert!((mean - 2.25).abs() < 0.01, "mean {mean}");
+    }
+
+    #[test]
+    fn moving_spheres_hold_still_outside_their_motion() {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let times = Interval::new(2.0, 4.0);
+        let sphere = Sphere::new_moving(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 4.0, 0.0), times, 1.0, material);
+
+        let center_at = |time: f64| {
+            let r = Ray::new_with_time(Point3::new(0.0, 20.0, 0.0), Vec3::new(0.0, -1.0, 0.0), time);
+            let mut rec = HitRecord::new();
+            assert!(sphere.hit(&r, &mut Interval::new(0.001, f64::INFINITY), &mut rec));
+            rec.p.y() - 1.0
+        };
+        for (time, expected) in [(0.0, 0.0), (2.0, 0.0), (3.0, 2.0), (3.5, 3.0), (4.0, 4.0), (9.0, 4.0)] {
+            assert!((center_at(time) - expected).abs() < 1e-9, "time {time}");
+        }
+
+        // The box covers the whole motion and nothing past it
+        let bbox = sphere.bounding_box();
+        assert!((bbox.y.min + 1.0).abs() < 1e-9 && (bbox.y.max - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn moving_lights_are_sampled_where_they_are_at_the_ray_time() {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new_dynamic(Point3::new(0.0, 0.0, 0.0), Point3::new(10.0, 0.0, 0.0), 1.0, material);
+        let origin = Point3::new(5.0, 10.0, 0.0);
+
+        let mut sampler = IndependentSampler::new(3);
+        for time in [0.0, 0.5, 1.0] {
+            let center = Point3::new(10.0 * time, 0.0, 0.0);
+            for _ in 0..50 {
+                let direction = sphere.random(origin, time, &mut sampler);
+                assert!(sphere.pdf_value(origin, direction, time) > 0.0, "time {time}");
+                assert!(dot(unit_vector(direction), unit_vector(center - origin)) > 0.99, "time {time}");
+            }
+        }
+
+        // Towards where the sphere starts is empty once it has moved away
+        assert_eq!(sphere.pdf_value(origin, Point3::new(0.0, 0.0, 0.0) - origin, 1.0), 0.0);
+    }
+}
@@ -0,0 +1,268 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bvh::BVH;
+    use color::Color;
+    use hittable::{HitRecord, Hittable};
+    use interval::Interval;
+    use material::Lambertian;
+    use matrix::Matrix4;
+    use planar::{create_box, Quadrilateral};
+    use quaternion::Quaternion;
+    use ray::Ray;
+    use rayonetta::*;
+    use sampler::IndependentSampler;
+    use sphere::Sphere;
+    use transformations::{AnimatedTransform, Keyframe, RotateY, Transform};
+    use utils::{Rng, INFINITY};
+    use vec3::{dot, unit_vector, Point3, Vec3};
+
+    fn closest_hit(world: &dyn Hittable, r: &Ray) -> Option<HitRecord> {
+        let mut rec = HitRecord::new();
+        world.hit(r, &mut Interval::new(0.001, INFINITY), &mut rec).then_some(rec)
+    }
+
+    fn random_ray(rng: &mut Rng) -> Ray {
+        let origin = Point3::random_range(-6.0, 6.0, rng);
+        let target = Point3::random_range(-1.0, 1.0, rng);
+        Ray::new(origin, target - origin)
+    }
+
+    // Rotation, non-uniform scale and translation together
+    fn placement() -> Matrix4 {
+        Matrix4::translate(Vec3::new(1.0, -0.5, 0.25))
+            * Matrix4::rotate(Vec3::new(1.0, 2.0, -0.5), 35.0)
+            * Matrix4::scale(Vec3::new(1.5, 0.5, 0.8))
+    }
+
+    #[test]
+    fn matrices_compose_and_invert() {
+        let m = placement() * Matrix4::look_at(Point3::new(0.0, 1.0, 2.0), Point3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let product = m * m.inverse().unwrap();
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product.m[i][j] - expected).abs() < 1e-12, "{product:?}");
+            }
+        }
+
+        // A quarter turn about x takes y to z, and look_at points +z at the target
+        let turned = Matrix4::rotate(Vec3::new(1.0, 0.0, 0.0), 90.0).transform_vector(Vec3::new(0.0, 1.0, 0.0));
+        assert!((turned - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-12);
+        let look = Matrix4::look_at(Point3::new(1.0, 1.0, 1.0), Point3::new(1.0, 1.0, 5.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!((look.transform_point(Point3::new(0.0, 0.0, 2.0)) - Point3::new(1.0, 1.0, 3.0)).length() < 1e-12);
+
+        assert!(Matrix4::scale(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+        assert!((placement().linear_determinant() - 1.5 * 0.5 * 0.8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rotation_about_y_matches_rotate_y() {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let object = create_box(Point3::new(-1.0, -0.5, -0.2), Point3::new(0.7, 1.0, 1.2), material);
+        let rotate_y = RotateY::new(object.clone(), 30.0);
+        let transform = Transform::new(object, Matrix4::rotate(Vec3::new(0.0, 1.0, 0.0), 30.0)).unwrap();
+
+        let mut rng = Rng::new(2);
+        for _ in 0..500 {
+            let r = random_ray(&mut rng);
+            match (closest_hit(&transform, &r), closest_hit(&rotate_y, &r)) {
+                (Some(a), Some(b)) => {
+                    assert!((a.t - b.t).abs() < 1e-9);
+                    assert!((a.normal - b.normal).length() < 1e-9);
+                    assert_eq!(a.front_face, b.front_face);
+                }
+                (None, None) => {}
+                _ => panic!("Transform and RotateY disagree"),
+            }
+        }
+    }
+
+    #[test]
+    fn scaled_spheres_are_ellipsoids() {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let sphere = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material));
+        let ellipsoid = Transform::new(sphere, placement()).unwrap();
+        let inverse = placement().inverse().unwrap();
+
+        let mut rng = Rng::new(4);
+        let mut hits = 0;
+        for _ in 0..500 {
+            let r = random_ray(&mut rng);
+            let Some(rec) = closest_hit(&ellipsoid, &r) else { continue };
+            hits += 1;
+
+            // On the surface, and with the normal of the implicit surface |inverse * p| = 1
+            let local = inverse.transform_point(rec.p);
+            assert!((local.length() - 1.0).abs() < 1e-9);
+            let gradient = unit_vector(inverse.transform_normal(local));
+            assert!((dot(rec.normal, gradient).abs() - 1.0).abs() < 1e-9);
+            assert!((rec.normal.length() - 1.0).abs() < 1e-9);
+            assert!(dot(r.direction(), rec.normal) < 0.0);
+            assert_eq!(rec.front_face, inverse.transform_point(r.origin()).length() > 1.0);
+            assert!((r.at(rec.t) - rec.p).length() < 1e-9);
+        }
+        assert!(hits > 100);
+
+        let bbox = ellipsoid.bounding_box();
+        for corner in [Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0)] {
+            let p = placement().transform_point(corner * 0.99);
+            assert!(bbox.x.contains(p.x()) && bbox.y.contains(p.y()) && bbox.z.contains(p.z()));
+        }
+    }
+
+    #[test]
+    fn transformed_lights_sample_like_the_placed_shape() {
+        // A transformed quad is the quad with transformed corner and edges
+        let light = Arc::new(Lambertian::new(Color::new(1.0, 1.0, 1.0)));
+        let (q, u, v) = (Point3::new(-0.5, 0.0, -0.5), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let m = placement();
+        let transformed = Transform::new(Arc::new(Quadrilateral::new(q, u, v, light.clone())), m).unwrap();
+        let placed = Quadrilateral::new(m.transform_point(q), m.transform_vector(u), m.transform_vector(v), light);
+
+        let origin = Point3::new(0.5, 4.0, 1.0);
+        let mut sampler = IndependentSampler::new(9);
+        for _ in 0..200 {
+            let direction = transformed.random(origin, 0.0, &mut sampler);
+            let expected = placed.pdf_value(origin, direction, 0.0);
+            assert!(expected > 0.0);
+            assert!((transformed.pdf_value(origin, direction, 0.0) - expected).abs() < 1e-9 * expected);
+            assert!((transformed.pdf_value(origin, 3.0 * direction, 0.0) - expected).abs() < 1e-9 * expected);
+        }
+    }
+
+    #[test]
+    fn instances_share_one_object() {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let shape = create_box(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0), material);
+
+        let mut instances: Vec<Arc<dyn Hittable>> = (0..10)
+            .map(|i| {
+                let m = Matrix4::translate(Vec3::new(3.0 * i as f64, 0.0, 0.0))
+                    * Matrix4::scale(Vec3::new(1.0, 1.0 + i as f64, 1.0));
+                Arc::new(Transform::new(shape.clone(), m).unwrap()) as Arc<dyn Hittable>
+            })
+            .collect();
+        let world = BVH::new(&mut instances);
+
+        for i in 0..10 {
+            let r = Ray::new(Point3::new(3.0 * i as f64 + 0.5, 20.0, 0.5), Vec3::new(0.0, -1.0, 0.0));
+            let rec = closest_hit(&world, &r).unwrap();
+            assert!((rec.p.y() - (1.0 + i as f64)).abs() < 1e-9);
+            assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+        }
+    }
+
+    fn assert_same_matrix(a: &Matrix4, b: &Matrix4) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((a.m[i][j] - b.m[i][j]).abs() < 1e-12, "{a:?} != {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn quaternions_interpolate_rotations() {
+        let axis = Vec3::new(1.0, -2.0, 0.5);
+        let q = Quaternion::from_axis_angle(axis, 120.0);
+        assert_same_matrix(&q.to_matrix(), &Matrix4::rotate(axis, 120.0));
+
+        // Halfway through a turn is half the angle, from either sign of the end rotation
+        let half = Quaternion::IDENTITY.slerp(&q, 0.5);
+        assert_same_matrix(&half.to_matrix(), &Matrix4::rotate(axis, 60.0));
+        let flipped = Quaternion { w: -q.w, x: -q.x, y: -q.y, z: -q.z };
+        assert_same_matrix(&Quaternion::IDENTITY.slerp(&flipped, 0.5).to_matrix(), &Matrix4::rotate(axis, 60.0));
+        assert!((Quaternion::IDENTITY.angle_to(&flipped) - 120f64.to_radians()).abs() < 1e-12);
+
+        let nearby = Quaternion::from_axis_angle(axis, 0.01);
+        assert_same_matrix(&Quaternion::IDENTITY.slerp(&nearby, 0.5).to_matrix(), &Matrix4::rotate(axis, 0.005));
+    }
+
+    fn tumbling_box() -> AnimatedTransform {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let shape = create_box(Point3::new(-1.0, -0.5, -0.5), Point3::new(1.0, 0.5, 0.5), material);
+
+        let start = Keyframe::new(0.0);
+        let mut middle = Keyframe::new(0.5);
+        middle.translation = Vec3::new(0.0, 1.0, 0.0);
+        middle.rotation = Quaternion::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 90.0);
+        let mut end = Keyframe::new(1.0);
+        end.translation = Vec3::new(2.0, 1.0, 0.0);
+        end.rotation = Quaternion::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 170.0);
+        end.scale = Vec3::new(0.5, 2.0, 1.0);
+
+        // Out of order on purpose
+        AnimatedTransform::new(shape, vec![end, start, middle]).unwrap()
+    }
+
+    #[test]
+    fn animated_objects_follow_the_ray_time() {
+        let animated = tumbling_box();
+        let inner = create_box(
+            Point3::new(-1.0, -0.5, -0.5),
+            Point3::new(1.0, 0.5, 0.5),
+            Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+        );
+
+        // Held still outside the keyframes
+        assert_same_matrix(&animated.at(-1.0).matrix(), &Matrix4::IDENTITY);
+        assert!((animated.at(0.25).translation - Vec3::new(0.0, 0.5, 0.0)).length() < 1e-12);
+        let up = Matrix4::translate(Vec3::new(0.0, 1.0, 0.0)) * Matrix4::rotate(Vec3::new(0.0, 0.0, 1.0), 90.0);
+        assert_same_matrix(&animated.at(0.5).matrix(), &up);
+        assert_same_matrix(&(animated.at(0.75).matrix() * animated.at(0.75).inverse()), &Matrix4::IDENTITY);
+
+        let mut rng = Rng::new(6);
+        let bbox = animated.bounding_box();
+        let mut hits = 0;
+        for i in 0..2000 {
+            let time = i as f64 / 2000.0;
+            let r = random_ray(&mut rng);
+            let r = Ray::new_with_time(r.origin(), r.direction(), time);
+
+            // Same as a fixed transform to where the box is at that time
+            let placed = Transform::new(inner.clone(), animated.at(time).matrix()).unwrap();
+            match (closest_hit(&animated, &r), closest_hit(&placed, &r)) {
+                (Some(a), Some(b)) => {
+                    assert!((a.t - b.t).abs() < 1e-9);
+                    assert!((a.normal - b.normal).length() < 1e-9);
+                    assert!(bbox.x.contains(a.p.x()) && bbox.y.contains(a.p.y()) && bbox.z.contains(a.p.z()));
+                    hits += 1;
+                }
+                (None, None) => {}
+                _ => panic!("animated and fixed transforms disagree at time {time}"),
+            }
+        }
+        assert!(hits > 200, "{hits} hits");
+    }
+
+    #[test]
+    fn motion_bounds_cover_every_corner() {
+        let animated = tumbling_box();
+        let bbox = animated.bounding_box();
+        for i in 0..=1000 {
+            let m = animated.at(i as f64 / 1000.0).matrix();
+            for corner in 0..8 {
+                let c = Point3::new(
+                    if corner & 1 == 0 { -1.0 } else { 1.0 },
+                    if corner & 2 == 0 { -0.5 } else { 0.5 },
+                    if corner & 4 == 0 { -0.5 } else { 0.5 },
+                );
+                let p = m.transform_point(c);
+                assert!(bbox.x.contains(p.x()) && bbox.y.contains(p.y()) && bbox.z.contains(p.z()));
+            }
+        }
+
+        // Not much larger than the swept volume either
+        assert!(bbox.x.size() < 5.0 && bbox.y.size() < 5.0 && bbox.z.size() < 4.0, "{bbox:?}");
+
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, material));
+        let mut flat = Keyframe::new(0.0);
+        flat.scale = Vec3::new(1.0, 0.0, 1.0);
+        assert!(AnimatedTransform::new(sphere.clone(), vec![flat]).is_err());
+        assert!(AnimatedTransform::new(sphere.clone(), vec![Keyframe::new(0.5), Keyframe::new(0.5)]).is_err());
+        assert!(AnimatedTransform::new(sphere, Vec::new()).is_err());
+    }
+}
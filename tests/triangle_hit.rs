@@ -0,0 +1,138 @@
+// Testing
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use color::Color;
+    use hittable::{HitRecord, Hittable};
+    use interval::Interval;
+    use material::Lambertian;
+    use mesh::{MeshData, TriangleMesh};
+    use ray::Ray;
+    use rayonetta::*;
+    use triangle::Triangle;
+    use utils::INFINITY;
+    use vec3::{Point3, Vec3};
+
+    // Unit square at z = 1 split along its diagonal
+    fn square() -> MeshData {
+        MeshData::new(
+            vec![
+                Point3::new(0.0, 0.0, 1.0),
+                Point3::new(1.0, 0.0, 1.0),
+                Point3::new(1.0, 1.0, 1.0),
+                Point3::new(0.0, 1.0, 1.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        )
+    }
+
+    #[test]
+    fn triangle_intersect_simple() {
+        let m = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let triangle = Triangle::new(Point3::new(0.0, 0.0, 1.0), Point3::new(1.0, 0.0, 1.0), Point3::new(0.0, 1.0, 1.0), m);
+
+        let r = Ray::new(Point3::new(0.25, 0.5, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let mut rec = HitRecord::new();
+
+        assert!(triangle.hit(&r, &mut Interval::new(0.001, INFINITY), &mut rec));
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+        assert!((rec.u - 0.25).abs() < 1e-12 && (rec.v - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn triangle_no_intersect() {
+        let m = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let triangle = Triangle::new(Point3::new(0.0, 0.0, 1.0), Point3::new(1.0, 0.0, 1.0), Point3::new(0.0, 1.0, 1.0), m);
+
+        let r = Ray::new(Point3::new(0.75, 0.75, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!triangle.hit(&r, &mut Interval::new(0.001, INFINITY), &mut HitRecord::new()));
+
+        let r = Ray::new(Point3::new(0.25, 0.25, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!triangle.hit(&r, &mut Interval::new(0.001, 0.5), &mut HitRecord::new()));
+    }
+
+    #[test]
+    fn mesh_is_watertight_along_shared_edge() {
+        let m = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mesh = TriangleMesh::new(square(), m).unwrap();
+
+        // Rays aimed exactly at the diagonal edge from an oblique origin
+        for k in 1..1000 {
+            let s = k as f64 / 1000.0;
+            let target = Point3::new(s, s, 1.0);
+            let origin = Point3::new(0.3, -0.7, -2.0);
+            let r = Ray::new(origin, target - origin);
+
+            assert!(mesh.hit(&r, &mut Interval::new(0.001, INFINITY), &mut HitRecord::new()));
+        }
+    }
+
+    #[test]
+    fn mesh_interpolates_normals_and_uvs() {
+        let m = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut data = square();
+        data.normals = vec![
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 0.0, -1.0),
+            Vec3::new(1.0, 0.0, -1.0),
+        ];
+        data.uvs = vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        let mesh = TriangleMesh::new(data, m).unwrap();
+        assert_eq!(mesh.triangle_count(), 2);
+
+        let r = Ray::new(Point3::new(0.2, 0.7, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let mut rec = HitRecord::new();
+        assert!(mesh.hit(&r, &mut Interval::new(0.001, INFINITY), &mut rec));
+
+        assert!((rec.u - 0.2).abs() < 1e-12 && (rec.v - 0.7).abs() < 1e-12);
+        // Counter-clockwise seen from +z, so the ray hits the back
+        assert!(!rec.front_face);
+        assert!(rec.normal.x() > 0.0 && rec.normal.z() < 0.0);
+        assert!((rec.normal.length() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn mesh_pdf_counts_every_face_along_the_ray() {
+        // Twenty stacked unit squares at z = 1 to 20, every one of them in the way
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for layer in 0..20 {
+            let z = (layer + 1) as f64;
+            let first = positions.len();
+            positions.extend([
+                Point3::new(0.0, 0.0, z),
+                Point3::new(1.0, 0.0, z),
+                Point3::new(1.0, 1.0, z),
+                Point3::new(0.0, 1.0, z),
+            ]);
+            indices.extend([[first, first + 1, first + 2], [first, first + 2, first + 3]]);
+        }
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mesh = TriangleMesh::new(MeshData::new(positions, indices), material).unwrap();
+        assert_eq!(mesh.area(), 20.0);
+
+        // Each layer adds distance^2 / (cosine * area), with the layer at z = k a distance k |d| away
+        let origin = Point3::new(0.1, 0.1, 0.0);
+        let direction = Vec3::new(0.02, 0.01, 1.0);
+        let length = direction.length();
+        let expected: f64 = (1..=20).map(|k| (k * k) as f64 * length * length * length / 20.0).sum();
+        let pdf = mesh.pdf_value(origin, direction, 0.0);
+        assert!((pdf - expected).abs() < 1e-9 * expected, "{pdf} != {expected}");
+
+        assert_eq!(mesh.pdf_value(origin, Vec3::new(0.0, 0.0, -1.0), 0.0), 0.0);
+        assert_eq!(mesh.pdf_value(origin, Vec3::new(1.0, 0.0, 0.1), 0.0), 0.0);
+    }
+
+    #[test]
+    fn mesh_rejects_bad_indices() {
+        let m = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut data = square();
+        data.indices.push([0, 3, 4]);
+
+        assert!(TriangleMesh::new(data, m.clone()).is_err());
+        assert!(TriangleMesh::new(MeshData::default(), m).is_err());
+    }
+}